@@ -1,10 +1,15 @@
 use std::io;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs;
 use std::iter::repeat;
 use std::time::{self, SystemTime, UNIX_EPOCH};
 use std::thread;
 use serde_json::json;
 use structopt::StructOpt;
 use linefeed::{Interface, ReadResult};
+use rand::{Rng, distributions::Alphanumeric};
+use rules::bundle::{GameBundle, verify_bundle};
 
 #[derive(Debug, StructOpt)]
 #[structopt(name = "example", about = "An example of StructOpt usage.")]
@@ -15,18 +20,57 @@ struct Cli {
 	instance: String,
 }
 
+// how many times to check for a match (every 2 seconds) after joining the queue
+const QUEUE_POLLS: usize = 15;
+// how many times to check that a move we made is visible (every half second)
+const GOSSIP_POLLS: usize = 8;
+// how many times to check for new moves (every 2 seconds) when watching a game
+const WATCH_POLLS: usize = 30;
+
 static COMMANDS: &[(&str, &str)] = &[
     ("help",             "Displays this the help page"),
     ("join_game",        "Set the game to make moves against, usage: join_game <game_address>"),
     ("new_game",         "Create a new game to play with an opponent, usage: new_game <opponent_address>"),
+    ("games",            "List all of your games and their status"),
+    ("rematch",          "Start a new game against the same opponent once the current game has finished"),
+    ("leaderboard",      "Show the players with the most games won by bringing every token home"),
+    ("rating",           "Show the Elo rating and rating history of yourself or another agent. Usage: rating [agent_address]"),
+    ("profile",          "Show your profile, or set your nickname and bio so other players see them instead of your address. Usage: profile [<nickname> <bio>]"),
+    ("players",          "List the nicknames of every agent who has set a profile"),
+    ("block",            "Hide an agent's proposals and responses and refuse games with them. Usage: block <agent_address>"),
+    ("unblock",          "Stop blocking an agent. Usage: unblock <agent_address>"),
+    ("blocked",          "List the agents you have blocked"),
     ("moves",            "Display the set of moves this game supports"),
     ("make_move",        "Make a move in this game, usage: make_move <move_json>"),
+    ("watch",            "Wait for the next move in the current game and show it"),
+    ("replay",           "Step through the current game move by move, use n (or enter) and p to go forwards and back and q to stop"),
+    ("export",           "Save the current game with the signatures of its moves to a file, usage: export <file>"),
+    ("verify",           "Check the hashes, signatures and moves of a game saved with export without asking the conductor, usage: verify <file>"),
+    ("say",              "Send a chat message in the current game, usage: say <text>"),
+    ("flip",             "Flip a coin for who moves first. Run once to commit and again, after your opponent commits, to reveal"),
  
     ("create_proposal",  "Publicly publish that you are looking for someone to play with. Usage: post_propoal <message>"),
     ("accept_proposal",   "Accept a propsal. This will start a new game. Usage: accept_proposal <proposal_hash>"),
-    ("get_proposals",    "Get all of the public proposals that are current"),
+    ("get_proposals",    "Get the public proposals that are current, newest first"),
     ("check_responses",  "Given a proposal hash find the responses. Usage: check_responses <proposal_hash>"),
+    ("more",             "Show the next page of results from the last get_proposals, check_responses, leaderboard or players"),
     ("remove_proposal",  "Remove a proposal that you authored given its hash. Usage: remove_proposal <proposal_hash>"),    
+
+    ("queue",            "Join the queue to be matched with the next available player"),
+    ("leave_queue",      "Leave the matchmaking queue"),
+
+    ("challenge",        "Challenge a specific agent to a game. Usage: challenge <agent_address> <message>"),
+    ("challenges",       "List the challenges other agents have sent you"),
+    ("accept_challenge", "Accept a challenge. This will start a new game. Usage: accept_challenge <challenge_hash>"),
+    ("decline_challenge","Decline a challenge sent to you. Usage: decline_challenge <challenge_hash>"),
+    ("check_challenge",  "See if a challenge you sent was accepted. Usage: check_challenge <challenge_hash>"),
+
+    ("tournament",       "Organise a tournament. Usage: tournament <RoundRobin|Swiss> <rounds> <name> <agent_address> [agent_address...]"),
+    ("register",         "Register for a tournament you were invited to. Usage: register <tournament_hash>"),
+    ("pairings",         "Show who plays who in a round of a tournament. Usage: pairings <tournament_hash> <round>"),
+    ("start_round",      "Start the next round of a tournament you organise. Usage: start_round <tournament_hash> <round>"),
+    ("tournament_result","Count a finished game towards a tournament. Usage: tournament_result <tournament_hash> <game_hash>"),
+    ("standings",        "Show the standings of a tournament. Usage: standings <tournament_hash>"),
  
     ("exit",             "Exit this CLI. Holochain will persist state so games can be resumed later."),
 ];
@@ -40,6 +84,26 @@ fn main() -> io::Result<()> {
     let make_move = holochain_call_generator(cli.url.clone(), cli.instance.clone(), "main".into(), "make_move".into());
     let create_game = holochain_call_generator(cli.url.clone(), cli.instance.clone(), "main".into(), "create_game".into());
     let render_game = holochain_call_generator(cli.url.clone(), cli.instance.clone(), "main".into(), "render_state".into());
+    let export_game_bundle = holochain_call_generator(cli.url.clone(), cli.instance.clone(), "main".into(), "export_game_bundle".into());
+    let sync_game = holochain_call_generator(cli.url.clone(), cli.instance.clone(), "main".into(), "sync_game".into());
+    let get_notifications = holochain_call_generator(cli.url.clone(), cli.instance.clone(), "main".into(), "get_notifications".into());
+    let send_game_message = holochain_call_generator(cli.url.clone(), cli.instance.clone(), "main".into(), "send_game_message".into());
+    let get_game_messages = holochain_call_generator(cli.url.clone(), cli.instance.clone(), "main".into(), "get_game_messages".into());
+    let get_moves_since = holochain_call_generator(cli.url.clone(), cli.instance.clone(), "main".into(), "get_moves_since".into());
+    let get_state = holochain_call_generator(cli.url.clone(), cli.instance.clone(), "main".into(), "get_state".into());
+    let render_game_at = holochain_call_generator(cli.url.clone(), cli.instance.clone(), "main".into(), "render_state_at".into());
+    let save_coin_secret = holochain_call_generator(cli.url.clone(), cli.instance.clone(), "main".into(), "save_coin_secret".into());
+    let get_coin_secret = holochain_call_generator(cli.url.clone(), cli.instance.clone(), "main".into(), "get_coin_secret".into());
+    let rematch = holochain_call_generator(cli.url.clone(), cli.instance.clone(), "main".into(), "rematch".into());
+    let get_my_games = holochain_call_generator(cli.url.clone(), cli.instance.clone(), "main".into(), "get_my_games".into());
+    let get_leaderboard = holochain_call_generator(cli.url.clone(), cli.instance.clone(), "main".into(), "get_leaderboard".into());
+    let get_rating = holochain_call_generator(cli.url.clone(), cli.instance.clone(), "main".into(), "get_rating".into());
+    let set_profile = holochain_call_generator(cli.url.clone(), cli.instance.clone(), "main".into(), "set_profile".into());
+    let get_profile = holochain_call_generator(cli.url.clone(), cli.instance.clone(), "main".into(), "get_profile".into());
+    let get_agent_directory = holochain_call_generator(cli.url.clone(), cli.instance.clone(), "main".into(), "get_agent_directory".into());
+    let block_agent = holochain_call_generator(cli.url.clone(), cli.instance.clone(), "main".into(), "block_agent".into());
+    let unblock_agent = holochain_call_generator(cli.url.clone(), cli.instance.clone(), "main".into(), "unblock_agent".into());
+    let get_blocked_agents = holochain_call_generator(cli.url.clone(), cli.instance.clone(), "main".into(), "get_blocked_agents".into());
 
     // matchmaking funcs
     let create_proposal = holochain_call_generator(cli.url.clone(), cli.instance.clone(), "main".into(), "create_proposal".into());
@@ -47,6 +111,20 @@ fn main() -> io::Result<()> {
     let accept_proposal = holochain_call_generator(cli.url.clone(), cli.instance.clone(), "main".into(), "accept_proposal".into());
     let check_responses = holochain_call_generator(cli.url.clone(), cli.instance.clone(), "main".into(), "check_responses".into());
     let _remove_proposal = holochain_call_generator(cli.url.clone(), cli.instance.clone(), "main".into(), "remove_proposal".into());
+    let join_queue = holochain_call_generator(cli.url.clone(), cli.instance.clone(), "main".into(), "join_queue".into());
+    let leave_queue = holochain_call_generator(cli.url.clone(), cli.instance.clone(), "main".into(), "leave_queue".into());
+    let find_match = holochain_call_generator(cli.url.clone(), cli.instance.clone(), "main".into(), "find_match".into());
+    let create_challenge = holochain_call_generator(cli.url.clone(), cli.instance.clone(), "main".into(), "create_challenge".into());
+    let get_incoming_challenges = holochain_call_generator(cli.url.clone(), cli.instance.clone(), "main".into(), "get_incoming_challenges".into());
+    let accept_challenge = holochain_call_generator(cli.url.clone(), cli.instance.clone(), "main".into(), "accept_challenge".into());
+    let decline_challenge = holochain_call_generator(cli.url.clone(), cli.instance.clone(), "main".into(), "decline_challenge".into());
+    let check_challenge = holochain_call_generator(cli.url.clone(), cli.instance.clone(), "main".into(), "check_challenge".into());
+    let create_tournament = holochain_call_generator(cli.url.clone(), cli.instance.clone(), "main".into(), "create_tournament".into());
+    let register_for_tournament = holochain_call_generator(cli.url.clone(), cli.instance.clone(), "main".into(), "register_for_tournament".into());
+    let get_pairings = holochain_call_generator(cli.url.clone(), cli.instance.clone(), "main".into(), "get_pairings".into());
+    let start_round = holochain_call_generator(cli.url.clone(), cli.instance.clone(), "main".into(), "start_round".into());
+    let record_tournament_result = holochain_call_generator(cli.url.clone(), cli.instance.clone(), "main".into(), "record_tournament_result".into());
+    let get_standings = holochain_call_generator(cli.url.clone(), cli.instance.clone(), "main".into(), "get_standings".into());
    
 
     let interface = Interface::new("Holochain generic game")?;
@@ -62,14 +140,22 @@ fn main() -> io::Result<()> {
     println!("");
     println!("");
 
-    match whoami(json!({})) {
+    let my_addr = match whoami(json!({})) {
     	Ok(agent_addr) => {
     		println!("Your agent address is {}\n\nSend this to other players so they can invite you to a game.", agent_addr);
+            agent_addr.as_str().unwrap_or_default().to_string()
     	},
     	Err(_e) => {
     		println!("No holochain instance named {} running on {}. Check the conductor is running and the instanceId in the conductor config is correct.", cli.instance, cli.url);
     		return Ok(());
     	}
+    };
+
+    if let Ok(profile) = get_profile(json!({"agent": my_addr})) {
+        match profile["nickname"].as_str() {
+            Some(nickname) => println!("Playing as {}", nickname),
+            None => println!("Use \"profile <nickname> <bio>\" to choose the name other players see."),
+        }
     }
 
     println!("");
@@ -78,6 +164,15 @@ fn main() -> io::Result<()> {
 	interface.set_prompt("No game> ")?;
 
 	let mut current_game: Option<String> = None;
+	// the last paged command and the arguments that fetch its next page
+	let mut paging: Option<(&str, serde_json::Value)> = None;
+	let mut in_queue = false;
+	// only tell the player about notifications which arrive while the CLI is running
+	let mut seen_notifications = count_items(get_notifications(json!({})));
+	// the game being watched and the address of the last move seen in it
+	let mut last_seen_move: Option<(String, serde_json::Value)> = None;
+	// the game whose chat is being shown and how many of its messages have been printed
+	let mut shown_messages: (String, usize) = (String::new(), 0);
 
  	while let ReadResult::Input(line) = interface.read_line()? {
 
@@ -86,6 +181,7 @@ fn main() -> io::Result<()> {
 		}
 
         let (cmd, args) = split_first_word(&line);
+        let names = Names::new(&get_profile);
 
         let result: Result<(), String> = match cmd {
             "help" => {
@@ -102,7 +198,13 @@ fn main() -> io::Result<()> {
             	if is_hash(args) {
             		println!("Setting current game hash to {}", args);
             		current_game = Some(args.into());
-                    Ok(())
+                    // make sure our chain holds every move so far or our next move won't validate
+                    sync_game(json!({"game_address": args})).map(|report| {
+                        let committed = report["moves_committed"].as_array().map(|moves| moves.len()).unwrap_or(0);
+                        if committed > 0 {
+                            println!("Caught up on {} moves", committed);
+                        }
+                    })
             	} else {
             		Err("argument must be a valid address".into())
             	}
@@ -120,21 +222,139 @@ fn main() -> io::Result<()> {
             		Err("argument must be valid agent address of an opponent.".into())
             	}
             }
+            "rematch" => {
+                if let Some(game_addr) = current_game.clone() {
+                    rematch(json!({"game_address": game_addr})).map(|rematch_addr| {
+                        println!("Rematch started with address: {}", rematch_addr);
+                        current_game = rematch_addr.as_str().map(|s| s.to_string());
+                    })
+                } else {
+                    Err("No game to rematch. use the \"join_game\" command.".into())
+                }
+            },
+            "games" => {
+                get_my_games(json!({})).and_then(|result| {
+                    let games = as_list(&result, "games")?;
+                    println!("Your games: \n");
+                    games.iter().for_each(|game| {
+                        let status = if game["finished"].as_bool().unwrap_or(false) {
+                            match game["outcome"].as_str() {
+                                Some("Draw") => "finished, drawn".to_string(),
+                                Some("Aborted") => "aborted".to_string(),
+                                _ => if game["winner"].as_str() == Some(my_addr.as_str()) {
+                                    "finished, you won".to_string()
+                                } else {
+                                    "finished, you lost".to_string()
+                                },
+                            }
+                        } else {
+                            match game["whose_turn"].as_str() {
+                                Some(player) if player == my_addr => "your turn".to_string(),
+                                Some(_) => "opponent's turn".to_string(),
+                                None => "nobody has moved yet".to_string(),
+                            }
+                        };
+                        println!("[{}] : {{ Opponent: {}, Created: {}, Status: {} }}", game["address"].as_str().unwrap_or_default(), names.of(&game["opponent"]), game["created_at"], status);
+                    });
+                    println!("use \"join_game\" with any of the listed addresses to resume.\n");
+                    Ok(())
+                })
+            },
+            "leaderboard" => {
+                let call_args = json!({});
+                get_leaderboard(call_args.clone()).and_then(|page| {
+                    println!("Leaderboard: \n");
+                    print_leaderboard(&names, &page)?;
+                    paging = next_page("get_leaderboard", call_args, &page);
+                    Ok(())
+                })
+            },
+            "rating" => {
+                let agent = if args.is_empty() { my_addr.as_str() } else { args };
+                if is_agent_addr(agent) {
+                    get_rating(json!({"agent": agent})).and_then(|rating| {
+                        let history = as_list(&rating["history"], "rating history")?;
+                        println!("Rating of {}: {:.0}\n", names.of(&json!(agent)), rating["rating"].as_f64().unwrap_or_default());
+                        history.iter().for_each(|change| {
+                            println!("[{}] : {{ Opponent: {}, Score: {}, Rating: {:.0} }}", change["game"].as_str().unwrap_or_default(), names.of(&change["opponent"]), change["score"], change["rating"].as_f64().unwrap_or_default());
+                        });
+                        println!();
+                        Ok(())
+                    })
+                } else {
+                    Err("argument must be a valid agent address.".into())
+                }
+            },
+            "profile" => {
+                if args.is_empty() {
+                    get_profile(json!({"agent": my_addr})).map(|profile| {
+                        if profile.is_null() {
+                            println!("You have not set a profile yet, or another player claimed your nickname first. Usage: profile <nickname> <bio>");
+                        } else {
+                            println!("{} : {}", profile["nickname"].as_str().unwrap_or_default(), profile["bio"].as_str().unwrap_or_default());
+                        }
+                    })
+                } else {
+                    let (nickname, bio) = split_first_word(args);
+                    set_profile(json!({"nickname": nickname, "bio": bio, "preferred_variant": null, "timestamp": current_timestamp()})).map(|_| {
+                        println!("Other players will now see you as {}", nickname);
+                    })
+                }
+            },
+            "players" => {
+                let call_args = json!({});
+                get_agent_directory(call_args.clone()).and_then(|page| {
+                    println!("Players: \n");
+                    print_directory(&page)?;
+                    paging = next_page("get_agent_directory", call_args, &page);
+                    Ok(())
+                })
+            },
+            "block" => {
+                if is_agent_addr(args) {
+                    block_agent(json!({"agent": args, "timestamp": current_timestamp()})).map(|_| {
+                        println!("Blocked {}", names.of(&json!(args)));
+                    })
+                } else {
+                    Err("argument must be a valid agent address.".into())
+                }
+            },
+            "unblock" => {
+                if is_agent_addr(args) {
+                    unblock_agent(json!({"agent": args, "timestamp": current_timestamp()})).map(|_| {
+                        println!("Unblocked {}", names.of(&json!(args)));
+                    })
+                } else {
+                    Err("argument must be a valid agent address.".into())
+                }
+            },
+            "blocked" => {
+                get_blocked_agents(json!({})).and_then(|agents| {
+                    let agents = as_list(&agents, "blocked agents")?;
+                    println!("You have blocked: \n");
+                    agents.iter().for_each(|agent| {
+                        println!("{} : {}", agent.as_str().unwrap_or_default(), names.of(agent));
+                    });
+                    println!();
+                    Ok(())
+                })
+            },
             "moves" => {
-            	valid_moves(json!({})).map(|result| {
+            	valid_moves(json!({})).and_then(|result| {
+	            	let moves = as_list(&result, "valid moves")?;
 	            	println!("The valid moves are:");
-	            	result.as_array().unwrap()
-	            	.iter()
-	            	.for_each(|elem| {
+	            	moves.iter().for_each(|elem| {
 	            		println!("- {}", elem);
 	            	});
                     println!();
+                    Ok(())
             	})
             },
             "make_move" => {
             	if let Some(current_game) = current_game.clone() {
             		let move_json: serde_json::Value = serde_json::from_str(args).unwrap_or(serde_json::Value::Null);
 	            	println!("making move: {:?}", args);
+	            	let moves_before = count_moves(&get_state, &current_game);
 	            	make_move(json!({
 		            	"new_move": {
 		            		"game": current_game,
@@ -144,8 +364,13 @@ fn main() -> io::Result<()> {
 	            	})).map(|_| {
                         println!("Move cast successfully");
                         println!("Waiting for gossip...");
-                        // wait a bit so it displays correctly
-                        thread::sleep(time::Duration::from_millis(4000));
+                        // wait until the move can be seen so it displays correctly
+                        for _ in 0..GOSSIP_POLLS {
+                            if count_moves(&get_state, &current_game) > moves_before {
+                                break
+                            }
+                            thread::sleep(time::Duration::from_millis(500));
+                        }
                         println!("OK!")
                     })
                 }
@@ -153,35 +378,189 @@ fn main() -> io::Result<()> {
             		Err("No game set to make moves on. use the \"join_game\" command.".into())
             	}
             },
+            "watch" => {
+                if let Some(current_game) = current_game.clone() {
+                    // only fetch the whole game the first time it is watched
+                    let mut last_known = match &last_seen_move {
+                        Some((game, address)) if *game == current_game => Ok(address.clone()),
+                        _ => get_moves_since(json!({"game_address": current_game}))
+                            .map(|moves| moves.as_array().and_then(|moves| moves.last()).map(|last| last["address"].clone()).unwrap_or_default()),
+                    };
+                    println!("Watching for new moves...");
+                    let mut seen_new_move = false;
+                    for _ in 0..WATCH_POLLS {
+                        let new_moves = last_known.clone().and_then(|address| {
+                            get_moves_since(json!({"game_address": current_game, "last_known_move_address": address}))
+                        });
+                        if let Some(new_moves) = new_moves.as_ref().ok().and_then(|moves| moves.as_array()) {
+                            for new_move in new_moves {
+                                println!("Move {}: {}", new_move["index"], new_move["entry"]["move_type"]);
+                                last_known = Ok(new_move["address"].clone());
+                                seen_new_move = true;
+                            }
+                        }
+                        if seen_new_move {
+                            break
+                        }
+                        thread::sleep(time::Duration::from_millis(2000));
+                    }
+                    if !seen_new_move {
+                        println!("No new moves yet");
+                    }
+                    last_known.map(|address| {
+                        last_seen_move = Some((current_game, address));
+                    })
+                } else {
+                    Err("No game to watch. use the \"join_game\" command.".into())
+                }
+            },
+            "replay" => {
+                if let Some(current_game) = current_game.clone() {
+                    let total = count_moves(&get_state, &current_game);
+                    let mut index = 0;
+                    interface.set_prompt("replay (n/p/q)> ")?;
+                    loop {
+                        match render_game_at(json!({"game_address": current_game, "index": index})) {
+                            Ok(rendered) => println!("{}", rendered.as_str().unwrap_or_default()),
+                            Err(err) => println!("Error: {}", err),
+                        }
+                        match interface.read_line()? {
+                            ReadResult::Input(input) => match input.trim() {
+                                "" | "n" => if index < total { index += 1 } else { println!("That was the last move") },
+                                "p" => if index > 0 { index -= 1 } else { println!("This is the start of the game") },
+                                "q" => break,
+                                _ => println!("Use n for the next move, p for the previous move and q to stop"),
+                            },
+                            _ => break,
+                        }
+                    }
+                    Ok(())
+                } else {
+                    Err("No game to replay. use the \"join_game\" command.".into())
+                }
+            },
+            "export" => {
+                if let Some(current_game) = current_game.clone() {
+                    export_game_bundle(json!({"game_address": current_game})).and_then(|bundle| {
+                        fs::write(args, bundle.to_string()).map_err(|e| e.to_string())?;
+                        println!("Saved {} moves to {}", bundle["moves"].as_array().map(|moves| moves.len()).unwrap_or(0), args);
+                        Ok(())
+                    })
+                } else {
+                    Err("No game to export. use the \"join_game\" command.".into())
+                }
+            },
+            "verify" => {
+                // checked here rather than by the conductor so nobody has to trust the node that exported it
+                fs::read_to_string(args).map_err(|e| e.to_string())
+                    .and_then(|contents| serde_json::from_str::<GameBundle>(&contents).map_err(|e| e.to_string()))
+                    .map(|bundle| {
+                        let verification = verify_bundle(&bundle, |provenance, data| {
+                            holochain_dpki::utils::verify(provenance.source(), data.to_string(), provenance.signature()).unwrap_or(false)
+                        });
+                        if verification.problems.is_empty() {
+                            println!("The game is genuine");
+                        } else {
+                            println!("The game has problems:");
+                            verification.problems.iter().for_each(|problem| println!("- {}", problem));
+                        }
+                    })
+            },
+            "say" => {
+                if let Some(current_game) = current_game.clone() {
+                    send_game_message(json!({
+                        "game_address": current_game,
+                        "text": args,
+                        "timestamp": current_timestamp()
+                    })).map(|_| ())
+                } else {
+                    Err("No game to send a message in. use the \"join_game\" command.".into())
+                }
+            },
+            "flip" => {
+                if let Some(current_game) = current_game.clone() {
+                    // the secret is kept on this agent's chain so it can still be revealed after a restart,
+                    // saving returns the commitment to the secret already kept for the game if there is one
+                    let move_type = save_coin_secret(json!({"game_address": current_game, "secret": new_coin_secret()}))
+                        .and_then(|commitment| get_state(json!({"game_address": current_game})).map(|state| {
+                            let committed = state["moves"].as_array()
+                                .map(|moves| moves.iter().any(|game_move| game_move["move_type"]["CommitCoin"]["commitment"] == commitment))
+                                .unwrap_or(false);
+                            (committed, commitment)
+                        }))
+                        .and_then(|(committed, commitment)| if committed {
+                            get_coin_secret(json!({"game_address": current_game}))
+                                .map(|secret| json!({"RevealCoin": {"secret": secret}}))
+                        } else {
+                            Ok(json!({"CommitCoin": {"commitment": commitment}}))
+                        });
+                    move_type.and_then(|move_type| make_move(json!({
+                        "new_move": {
+                            "game": current_game,
+                            "move_type": move_type,
+                            "timestamp": current_timestamp()
+                        }
+                    }))).map(|_| println!("Coin flip move cast successfully"))
+                } else {
+                    Err("No game set to flip a coin in. use the \"join_game\" command.".into())
+                }
+            },
             "create_proposal" => {
                 println!("creating proposal with message {:?}", args);
-                let result = create_proposal(json!({"message": args}));
+                let result = create_proposal(json!({"message": args, "timestamp": current_timestamp()}));
                 println!("Create result: {:?}", result);
                 Ok(())
             },
             "get_proposals" => {
-                let result = get_proposals(json!({})).unwrap();
-                println!("Current game proposals: \n");
-                result.as_array().unwrap().iter().for_each(|r| {
-                    println!("[{}] : {{ Agent: {}, Message: {} }}", r["address"].as_str().unwrap(), r["entry"]["agent"], r["entry"]["message"]);
-                });
-                println!("\n");
-                Ok(())
+                let call_args = json!({"timestamp": current_timestamp()});
+                get_proposals(call_args.clone()).and_then(|page| {
+                    println!("Current game proposals: \n");
+                    print_proposals(&names, &page)?;
+                    paging = next_page("get_proposals", call_args, &page);
+                    Ok(())
+                })
             },
             "accept_proposal" => {
-                accept_proposal(json!({"proposal_addr": args, "created_at": current_timestamp()})).map(|game_addr| {
+                accept_proposal(json!({"proposal_addr": args, "created_at": current_timestamp()})).and_then(|game_addr| {
+                    let game_addr = as_address(&game_addr)?;
                     println!("Proposal accepted. Game created with address: {}", game_addr);
-                    current_game = Some(game_addr.as_str().unwrap().into());
+                    current_game = Some(game_addr);
+                    Ok(())
                 })
             },
             "check_responses" => {
-                let result = check_responses(json!({"proposal_addr": args})).unwrap();
-                println!("Proposal has the following responses: \n");
-                result.as_array().unwrap().iter().for_each(|response| {
-                    println!("[{}] : Agent: {}", response["address"], response["entry"]["player_1"]);
-                });
-                println!("use \"join_game\" with any of the listed addresses to join: \n");
-                Ok(())            
+                let call_args = json!({"proposal_addr": args});
+                check_responses(call_args.clone()).and_then(|page| {
+                    println!("Proposal has the following responses: \n");
+                    print_responses(&names, &page)?;
+                    paging = next_page("check_responses", call_args, &page);
+                    Ok(())
+                })
+            },
+            "more" => {
+                match paging.take() {
+                    Some((paged_cmd, call_args)) => {
+                        let result = match paged_cmd {
+                            "get_proposals" => get_proposals(call_args.clone()),
+                            "get_leaderboard" => get_leaderboard(call_args.clone()),
+                            "get_agent_directory" => get_agent_directory(call_args.clone()),
+                            _ => check_responses(call_args.clone()),
+                        };
+                        result.and_then(|page| {
+                            match paged_cmd {
+                                "get_proposals" => print_proposals(&names, &page),
+                                "get_leaderboard" => print_leaderboard(&names, &page),
+                                "get_agent_directory" => print_directory(&page),
+                                _ => print_responses(&names, &page),
+                            }?;
+                            paging = next_page(paged_cmd, call_args, &page);
+                            Ok(())
+                        })
+                    },
+                    None => {
+                        Err("Nothing more to show.".into())
+                    }
+                }
             },
             "remove_proposal" => {
                 println!("NOT IMPLEMENTED - this has been disabled until deletion but is fixed.");
@@ -190,6 +569,151 @@ fn main() -> io::Result<()> {
                 //     println!("Proposal successfully marked as deleted\n");
                 // })
             },
+            "queue" => {
+                join_queue(json!({"timestamp": current_timestamp()})).map(|_| {
+                    println!("Joined the queue, looking for an opponent...");
+                    in_queue = true;
+                    for _ in 0..QUEUE_POLLS {
+                        if let Ok(game_addr) = find_match(json!({"timestamp": current_timestamp()})) {
+                            if let Some(game_addr) = game_addr.as_str() {
+                                println!("Matched! Game created with address: {}", game_addr);
+                                current_game = Some(game_addr.into());
+                                in_queue = false;
+                                break
+                            }
+                        }
+                        thread::sleep(time::Duration::from_millis(2000));
+                    }
+                    if in_queue {
+                        println!("No opponent yet. You will stay in the queue and be told when you are matched.");
+                    }
+                })
+            },
+            "leave_queue" => {
+                leave_queue(json!({})).map(|_| {
+                    println!("Left the queue");
+                    in_queue = false;
+                })
+            },
+            "challenge" => {
+                let (opponent, message) = split_first_word(args);
+                if is_agent_addr(opponent) {
+                    create_challenge(json!({
+                        "opponent": opponent,
+                        "message": message,
+                        "timestamp": current_timestamp()
+                    })).map(|challenge_addr| {
+                        println!("Challenge sent: {}", challenge_addr);
+                        println!("use \"check_challenge\" with this address to see if it was accepted.");
+                    })
+                } else {
+                    Err("first argument must be valid agent address of an opponent.".into())
+                }
+            },
+            "challenges" => {
+                get_incoming_challenges(json!({})).and_then(|result| {
+                    let challenges = as_list(&result, "challenges")?;
+                    println!("You have been challenged by: \n");
+                    challenges.iter().for_each(|r| {
+                        println!("[{}] : {{ Agent: {}, Message: {} }}", r["address"].as_str().unwrap_or_default(), names.of(&r["entry"]["challenger"]), r["entry"]["message"]);
+                    });
+                    println!("use \"accept_challenge\" or \"decline_challenge\" with any of the listed addresses.\n");
+                    Ok(())
+                })
+            },
+            "accept_challenge" => {
+                accept_challenge(json!({"challenge_addr": args, "created_at": current_timestamp()})).and_then(|game_addr| {
+                    let game_addr = as_address(&game_addr)?;
+                    println!("Challenge accepted. Game created with address: {}", game_addr);
+                    current_game = Some(game_addr);
+                    Ok(())
+                })
+            },
+            "decline_challenge" => {
+                decline_challenge(json!({"challenge_addr": args})).map(|_| {
+                    println!("Challenge declined");
+                })
+            },
+            "check_challenge" => {
+                check_challenge(json!({"challenge_addr": args})).and_then(|result| {
+                    match as_list(&result, "games")?.first() {
+                        Some(response) => {
+                            println!("Challenge was accepted, use \"join_game {}\" to play.", as_address(&response["address"])?);
+                        },
+                        None => {
+                            println!("Challenge has not been accepted yet.");
+                        }
+                    }
+                    Ok(())
+                })
+            },
+            "tournament" => {
+                let words: Vec<&str> = args.split_whitespace().collect();
+                match (words.get(0), words.get(1).and_then(|rounds| rounds.parse::<u32>().ok()), words.get(2)) {
+                    (Some(format), Some(rounds), Some(name)) if words.len() > 4 && words[3..].iter().all(|agent| is_agent_addr(agent)) => {
+                        create_tournament(json!({
+                            "name": name,
+                            "participants": words[3..].to_vec(),
+                            "format": format,
+                            "rounds": rounds,
+                            "variant": "Finkel",
+                            "timestamp": current_timestamp()
+                        })).map(|tournament_addr| {
+                            println!("Tournament created: {}", tournament_addr);
+                            println!("Participants must \"register\" before you \"start_round {} 1\".", tournament_addr.as_str().unwrap_or_default());
+                        })
+                    },
+                    _ => Err("usage: tournament <RoundRobin|Swiss> <rounds> <name> <agent_address> [agent_address...]".into())
+                }
+            },
+            "register" => {
+                register_for_tournament(json!({"tournament_addr": args})).map(|_| {
+                    println!("Registered, you will be paired once the organiser starts a round");
+                })
+            },
+            "pairings" | "start_round" => {
+                let (tournament_addr, round) = split_first_word(args);
+                match round.parse::<u32>() {
+                    Ok(round) => {
+                        let call_args = json!({"tournament_addr": tournament_addr, "round": round});
+                        let started = if cmd == "start_round" {
+                            start_round(call_args.clone()).map(|_| println!("Round {} started", round))
+                        } else {
+                            Ok(())
+                        };
+                        started.and_then(|_| get_pairings(call_args)).and_then(|pairings| {
+                            let games = as_list(&pairings["pairings"], "pairings")?;
+                            println!("Round {} pairings: \n", round);
+                            games.iter().for_each(|pairing| {
+                                println!("[{}] : {} vs {}", pairing["game"].as_str().unwrap_or_default(), names.of(&pairing["player_1"]), names.of(&pairing["player_2"]));
+                            });
+                            if !pairings["bye"].is_null() {
+                                println!("Bye: {}", names.of(&pairings["bye"]));
+                            }
+                            println!();
+                            Ok(())
+                        })
+                    },
+                    Err(_) => Err("usage: pairings <tournament_hash> <round>".into())
+                }
+            },
+            "tournament_result" => {
+                let (tournament_addr, game_addr) = split_first_word(args);
+                record_tournament_result(json!({"tournament_addr": tournament_addr, "game_addr": game_addr})).map(|_| {
+                    println!("Result counted");
+                })
+            },
+            "standings" => {
+                get_standings(json!({"tournament_addr": args})).and_then(|standings| {
+                    let standings = as_list(&standings, "standings")?;
+                    println!("Standings: \n");
+                    standings.iter().enumerate().for_each(|(place, standing)| {
+                        println!("{}. {} : {{ Score: {}, Buchholz: {}, Wins: {}, Losses: {}, Byes: {} }}", place + 1, names.of(&standing["agent"]), standing["score"], standing["buchholz"], standing["wins"], standing["losses"], standing["byes"]);
+                    });
+                    println!();
+                    Ok(())
+                })
+            },
             "exit" => {
             	if let Some(current_game) = current_game.clone() {
 					println!("You can resume this game at a later date by using:\n\"join_game {}\"", current_game);
@@ -206,11 +730,42 @@ fn main() -> io::Result<()> {
             println!("Error: {}", e)
         }
 
+        // opponents who are online tell us about their moves directly
+        if let Ok(notifications) = get_notifications(json!({})) {
+            let notifications = notifications.as_array().cloned().unwrap_or_default();
+            for notification in notifications.iter().skip(seen_notifications) {
+                println!("Your opponent moved in game {}", notification["game"].as_str().unwrap_or_default());
+            }
+            seen_notifications = notifications.len();
+        }
+
+        if in_queue {
+            if let Ok(game_addr) = find_match(json!({"timestamp": current_timestamp()})) {
+                if let Some(game_addr) = game_addr.as_str() {
+                    println!("You have been matched! Game address: {}", game_addr);
+                    current_game = Some(game_addr.into());
+                    in_queue = false;
+                }
+            }
+        }
+
 		if let Some(current_game_string) = current_game.clone() {
  			interface.set_prompt(&format!("{}> ", current_game_string))?;
- 			match render_game(json!({"game_address": current_game_string.clone()})) {
+ 			match render_game(json!({"game_address": current_game_string.clone(), "timestamp": current_timestamp()})) {
  				Ok(render_result) => {
-            		println!("{}", render_result.as_str().unwrap());
+            		println!("{}", render_result.as_str().unwrap_or_default());
+                    // show any chat messages which arrived since the last render
+                    if shown_messages.0 != current_game_string {
+                        shown_messages = (current_game_string.clone(), 0);
+                    }
+                    if let Ok(messages) = get_game_messages(json!({"game_address": current_game_string.clone()})) {
+                        let messages = messages.as_array().cloned().unwrap_or_default();
+                        for message in messages.iter().skip(shown_messages.1) {
+                            let author = if message["author"].as_str() == Some(my_addr.as_str()) { "You".to_string() } else { names.of(&message["author"]) };
+                            println!("{}: {}", author, message["text"].as_str().unwrap_or_default());
+                        }
+                        shown_messages.1 = messages.len();
+                    }
  				},
  				Err(_e) => {
  					println!("No game is currently visible with that address.");
@@ -285,6 +840,56 @@ fn split_first_word(s: &str) -> (&str, &str) {
     }
 }
 
+fn print_proposals(names: &Names, page: &serde_json::Value) -> Result<(), String> {
+    as_list(&page["items"], "proposals")?.iter().for_each(|r| {
+        println!("[{}] : {{ Agent: {}, Message: {} }}", r["address"].as_str().unwrap_or_default(), names.of(&r["entry"]["agent"]), r["entry"]["message"]);
+    });
+    println!("\n");
+    Ok(())
+}
+
+fn print_responses(names: &Names, page: &serde_json::Value) -> Result<(), String> {
+    as_list(&page["items"], "responses")?.iter().for_each(|response| {
+        println!("[{}] : Agent: {}", response["address"], names.of(&response["entry"]["player_1"]));
+    });
+    println!("use \"join_game\" with any of the listed addresses to join: \n");
+    Ok(())
+}
+
+fn print_leaderboard(names: &Names, page: &serde_json::Value) -> Result<(), String> {
+    as_list(&page["items"], "leaderboard")?.iter().for_each(|entry| {
+        println!("{} : {{ Wins: {}, Losses: {}, Played: {}, Win %: {:.1} }}", names.of(&entry["agent"]), entry["wins"], entry["losses"], entry["games_played"], entry["win_percentage"].as_f64().unwrap_or_default());
+    });
+    println!("\n");
+    Ok(())
+}
+
+fn print_directory(page: &serde_json::Value) -> Result<(), String> {
+    as_list(&page["items"], "players")?.iter().for_each(|profile| {
+        println!("{} : {{ Agent: {}, Bio: {} }}", profile["nickname"].as_str().unwrap_or_default(), profile["agent"].as_str().unwrap_or_default(), profile["bio"]);
+    });
+    println!("\n");
+    Ok(())
+}
+
+// set up the arguments for the following page if there is one
+fn next_page<'a>(cmd: &'a str, mut call_args: serde_json::Value, page: &serde_json::Value) -> Option<(&'a str, serde_json::Value)> {
+    page["next_cursor"].as_str().map(|cursor| {
+        println!("There are more results, use \"more\" to see them.\n");
+        call_args["cursor"] = json!(cursor);
+        (cmd, call_args)
+    })
+}
+
+// the list a zome function returned, or an error to show if it returned something else
+fn as_list<'a>(value: &'a serde_json::Value, what: &str) -> Result<&'a Vec<serde_json::Value>, String> {
+	value.as_array().ok_or_else(|| format!("Holochain did not return a list of {}: {}", what, value))
+}
+
+fn as_address(value: &serde_json::Value) -> Result<String, String> {
+	value.as_str().map(|address| address.to_string()).ok_or_else(|| format!("Holochain did not return an address: {}", value))
+}
+
 fn is_hash(s: &str) -> bool {
 	s.starts_with("Qm") && s.len() == 46
 }
@@ -293,8 +898,48 @@ fn is_agent_addr(s: &str) -> bool {
 	s.starts_with("Hc") && s.len() == 63
 }
 
+// the nicknames looked up while running one command, so a listing only fetches each agent's profile once
+struct Names<'a> {
+	get_profile: &'a Box<Fn(serde_json::Value) -> Result<serde_json::Value, String>>,
+	cache: RefCell<HashMap<String, String>>,
+}
+
+impl<'a> Names<'a> {
+	fn new(get_profile: &'a Box<Fn(serde_json::Value) -> Result<serde_json::Value, String>>) -> Self {
+		Names{get_profile, cache: RefCell::new(HashMap::new())}
+	}
+
+	// the nickname of an agent, or their address if they haven't set a profile
+	fn of(&self, agent: &serde_json::Value) -> String {
+		let address = agent.as_str().unwrap_or_default();
+		if let Some(name) = self.cache.borrow().get(address) {
+			return name.clone()
+		}
+		let name = (self.get_profile)(json!({"agent": address})).ok()
+			.and_then(|profile| profile["nickname"].as_str().map(|nickname| nickname.to_string()))
+			.unwrap_or_else(|| address.to_string());
+		self.cache.borrow_mut().insert(address.to_string(), name.clone());
+		name
+	}
+}
+
+fn count_items(result: Result<serde_json::Value, String>) -> usize {
+	result.ok().and_then(|items| items.as_array().map(|items| items.len())).unwrap_or(0)
+}
+
+fn count_moves(get_state: &Box<Fn(serde_json::Value) -> Result<serde_json::Value, String>>, game: &str) -> usize {
+	get_state(json!({"game_address": game}))
+		.map(|state| state["moves"].as_array().map(|moves| moves.len()).unwrap_or(0))
+		.unwrap_or(0)
+}
+
 fn current_timestamp() -> u32 {
 	SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as u32
 }
 
+// a secret the opponent can't guess before it is revealed
+fn new_coin_secret() -> String {
+	rand::thread_rng().sample_iter(&Alphanumeric).take(32).collect()
+}
+
 /*=====  End of Helpers  ======*/
@@ -0,0 +1,177 @@
+use holochain_json_api::{
+    error::JsonError, json::JsonString,
+};
+use holochain_persistence_api::cas::content::{AddressableContent, Address};
+use holochain_core_types::{
+    entry::Entry,
+    signature::Provenance,
+};
+
+use crate::game::Game;
+use crate::game_move::Move;
+use crate::GameState;
+
+/// Everything needed to check a game without access to the DHT it was played on
+#[derive(Clone, Debug, Serialize, Deserialize, DefaultJson)]
+pub struct GameBundle {
+    pub game_address: Address,
+    pub game: Game,
+    // in the order they were played
+    pub moves: Vec<BundledMove>,
+    pub final_state: GameState,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, DefaultJson)]
+pub struct BundledMove {
+    pub address: Address,
+    pub entry: Move,
+    // the author's signatures over the move address, taken from its headers
+    pub signatures: Vec<Provenance>,
+}
+
+/// The result of checking a bundle, it is genuine if there are no problems
+#[derive(Clone, Debug, Serialize, Deserialize, DefaultJson)]
+pub struct BundleVerification {
+    pub problems: Vec<String>,
+    pub final_state: Option<GameState>,
+}
+
+/// Re-hash every entry, check the authors' signatures and replay the rules.
+/// `verify_signature` checks a signature over the given data, which is done differently in a zome and outside one.
+pub fn verify_bundle<F>(bundle: &GameBundle, verify_signature: F) -> BundleVerification
+    where F: Fn(&Provenance, &str) -> bool
+{
+    let mut problems = Vec::new();
+    if Entry::App("game".into(), bundle.game.clone().into()).address() != bundle.game_address {
+        problems.push("The game does not hash to its address".to_string());
+    }
+
+    let mut state = GameState::initial();
+    let mut previous_address = bundle.game_address.clone();
+    for (index, bundled) in bundle.moves.iter().enumerate() {
+        let game_move = &bundled.entry;
+        if Entry::App("move".into(), game_move.clone().into()).address() != bundled.address {
+            problems.push(format!("Move {} does not hash to its address", index));
+        }
+        let signed_by_author = bundled.signatures.iter().any(|provenance| {
+            provenance.source() == game_move.author && verify_signature(provenance, &bundled.address.to_string())
+        });
+        if !signed_by_author {
+            problems.push(format!("Move {} is not signed by its author", index));
+        }
+        if game_move.game != bundle.game_address || game_move.previous_move != previous_address {
+            problems.push(format!("Move {} does not follow on from the one before it", index));
+        }
+        if let Err(reason) = game_move.is_valid(bundle.game.clone(), state.clone()) {
+            problems.push(format!("Move {} is not valid: {}", index, reason));
+            return BundleVerification{problems, final_state: None}
+        }
+        state = state.evolve(bundle.game.clone(), game_move);
+        previous_address = bundled.address.clone();
+    }
+
+    if JsonString::from(state.clone()) != JsonString::from(bundle.final_state.clone()) {
+        problems.push("The final state does not match the moves".to_string());
+    }
+    BundleVerification{problems, final_state: Some(state)}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use holochain_core_types::signature::Signature;
+    use crate::game::{TimeControl, DEFAULT_INACTIVITY_WINDOW};
+    use crate::{MoveType, Outcome};
+
+    // stands in for the keystore, a signature is good if it names what was signed
+    fn signature_over(data: &str) -> Signature {
+        Signature::from(format!("signed {}", data))
+    }
+
+    fn fake_verifier(provenance: &Provenance, data: &str) -> bool {
+        provenance.signature() == signature_over(data)
+    }
+
+    fn bundle(moves: Vec<(&str, MoveType)>) -> GameBundle {
+        let game = Game {
+            player_1: "HcPlayerOne".into(),
+            player_2: "HcPlayerTwo".into(),
+            created_at: 1_560_000_000,
+            first_player: Some("HcPlayerOne".into()),
+            time_control: TimeControl::Unlimited,
+            inactivity_window: DEFAULT_INACTIVITY_WINDOW,
+        };
+        let game_address = Entry::App("game".into(), game.clone().into()).address();
+        let mut state = GameState::initial();
+        let mut previous_move = game_address.clone();
+        let moves = moves.into_iter().enumerate().map(|(index, (author, move_type))| {
+            let entry = Move {
+                game: game_address.clone(),
+                author: author.into(),
+                move_type,
+                previous_move: previous_move.clone(),
+                timestamp: game.created_at + 10 * (index as u32 + 1),
+            };
+            let address = Entry::App("move".into(), entry.clone().into()).address();
+            state = state.clone().evolve(game.clone(), &entry);
+            previous_move = address.clone();
+            BundledMove {
+                signatures: vec![Provenance::new(author.into(), signature_over(&address.to_string()))],
+                address,
+                entry,
+            }
+        }).collect();
+        GameBundle{game_address, game, moves, final_state: state}
+    }
+
+    fn resigned_game() -> GameBundle {
+        bundle(vec![
+            ("HcPlayerOne", MoveType::CreateToken{distance: 2}),
+            ("HcPlayerTwo", MoveType::Resign),
+        ])
+    }
+
+    #[test]
+    fn accepts_a_genuine_bundle() {
+        let verification = verify_bundle(&resigned_game(), fake_verifier);
+        assert!(verification.problems.is_empty(), "{:?}", verification.problems);
+        assert_eq!(verification.final_state.unwrap().outcome, Some(Outcome::Resigned{winner: "HcPlayerOne".into()}));
+    }
+
+    #[test]
+    fn notices_an_entry_which_does_not_match_its_address() {
+        let mut bundle = resigned_game();
+        bundle.moves[1].entry.timestamp += 1;
+        let verification = verify_bundle(&bundle, fake_verifier);
+        assert_eq!(verification.problems, vec!["Move 1 does not hash to its address".to_string()]);
+    }
+
+    #[test]
+    fn needs_the_authors_signature() {
+        let mut bundle = resigned_game();
+        bundle.moves[0].signatures = vec![Provenance::new("HcPlayerTwo".into(), signature_over(&bundle.moves[0].address.to_string()))];
+        let verification = verify_bundle(&bundle, fake_verifier);
+        assert_eq!(verification.problems, vec!["Move 0 is not signed by its author".to_string()]);
+    }
+
+    #[test]
+    fn stops_at_the_first_invalid_move() {
+        let bundle = bundle(vec![
+            ("HcPlayerTwo", MoveType::CreateToken{distance: 2}),
+            ("HcPlayerOne", MoveType::Resign),
+        ]);
+        let verification = verify_bundle(&bundle, fake_verifier);
+        assert_eq!(verification.problems.len(), 1);
+        assert!(verification.problems[0].starts_with("Move 0 is not valid"));
+        assert!(verification.final_state.is_none());
+    }
+
+    #[test]
+    fn checks_the_claimed_final_state() {
+        let mut bundle = resigned_game();
+        bundle.final_state = GameState::initial();
+        let verification = verify_bundle(&bundle, fake_verifier);
+        assert_eq!(verification.problems, vec!["The final state does not match the moves".to_string()]);
+        assert!(verification.final_state.is_some());
+    }
+}
@@ -0,0 +1,75 @@
+use holochain_json_api::{
+    error::JsonError, json::JsonString,
+};
+use holochain_persistence_api::cas::content::Address;
+
+#[derive(Clone, Debug, Serialize, Deserialize, DefaultJson)]
+pub struct Game {
+    pub player_1: Address,
+    pub player_2: Address,
+    pub created_at: u32,
+    // if set only this player may make the first move, otherwise the players flip a coin
+    pub first_player: Option<Address>,
+    pub time_control: TimeControl,
+    // seconds without a move after which the waiting player may claim the game
+    pub inactivity_window: u32,
+}
+
+/// Who the creator of a game wants to move first
+#[derive(Clone, Debug, Serialize, Deserialize, DefaultJson, PartialEq)]
+pub enum FirstMove {
+    Me,
+    Opponent,
+    // decided by a commit-reveal coin flip between the players
+    CoinFlip,
+}
+
+/// Unless a game says otherwise player_2, the agent who did not create it, moves first
+impl Default for FirstMove {
+    fn default() -> Self {
+        FirstMove::Opponent
+    }
+}
+
+impl FirstMove {
+    pub fn first_player(&self, me: &Address, opponent: &Address) -> Option<Address> {
+        match self {
+            FirstMove::Me => Some(me.clone()),
+            FirstMove::Opponent => Some(opponent.clone()),
+            FirstMove::CoinFlip => None,
+        }
+    }
+}
+
+/// Games which do not choose their own inactivity window use one week
+pub const DEFAULT_INACTIVITY_WINDOW: u32 = 7 * 24 * 60 * 60;
+
+/// How much thinking time the players get
+#[derive(Clone, Debug, Serialize, Deserialize, DefaultJson, PartialEq)]
+pub enum TimeControl {
+    // no clock
+    Unlimited,
+    // a fixed number of days to make each move
+    Correspondence{days_per_move: u32},
+    // a total time for the game which gains an increment after every move
+    Fischer{initial_seconds: u32, increment_seconds: u32},
+}
+
+impl TimeControl {
+    pub fn initial_clock(&self) -> Option<u32> {
+        match self {
+            TimeControl::Unlimited => None,
+            TimeControl::Correspondence{days_per_move} => Some(days_per_move * 24 * 60 * 60),
+            TimeControl::Fischer{initial_seconds, ..} => Some(*initial_seconds),
+        }
+    }
+
+    /// The clock of a player who had `remaining` seconds and took `elapsed` seconds to move
+    pub fn after_move(&self, remaining: u32, elapsed: u32) -> Option<u32> {
+        match self {
+            TimeControl::Unlimited => None,
+            TimeControl::Correspondence{..} => self.initial_clock(),
+            TimeControl::Fischer{increment_seconds, ..} => Some(remaining.saturating_sub(elapsed) + increment_seconds),
+        }
+    }
+}
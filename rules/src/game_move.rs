@@ -0,0 +1,23 @@
+use holochain_json_api::{
+    error::JsonError, json::JsonString,
+};
+use holochain_persistence_api::cas::content::Address;
+
+use crate::MoveType;
+
+#[derive(Clone, Debug, Serialize, Deserialize, DefaultJson)]
+pub struct MoveInput {
+	pub game: Address,
+	pub move_type: MoveType,
+	pub timestamp: u32,
+}
+
+
+#[derive(Clone, Debug, Serialize, Deserialize, DefaultJson, PartialEq)]
+pub struct Move {
+	pub game: Address,
+	pub author: Address,
+	pub move_type: MoveType,
+	pub previous_move: Address,
+	pub timestamp: u32,
+}
@@ -0,0 +1,32 @@
+/**
+ * The game types and rules shared by the zome and the CLI, so a game can be
+ * checked without a conductor as well as during validation.
+ */
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
+#[macro_use]
+extern crate holochain_json_derive;
+extern crate holochain_core_types;
+extern crate holochain_json_api;
+extern crate holochain_persistence_api;
+
+// This is where you would import your own game State, MoveType and state_reducer
+
+pub mod your_game;
+pub use your_game::{
+    GameState,
+    MoveType,
+    Outcome,
+    whose_turn,
+    coin_commitment,
+    VARIANT,
+};
+
+pub mod game;
+pub mod game_move;
+pub mod bundle;
+pub mod time;
+pub mod rating;
+pub mod pairing;
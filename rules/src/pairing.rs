@@ -0,0 +1,192 @@
+use holochain_persistence_api::cas::content::Address;
+
+// how many opponents the Swiss search may try before settling for greedy pairings
+const MAX_PAIRING_STEPS: usize = 10_000;
+
+/// Circle method: the first player stays put and everyone else rotates one seat per round.
+/// An odd number of players gets an empty seat and whoever sits opposite it has the bye.
+pub fn round_robin_pairs(players: Vec<Address>, round: u32) -> (Vec<(Address, Address)>, Option<Address>) {
+    let mut seats: Vec<Option<Address>> = players.into_iter().map(Some).collect();
+    if seats.len() % 2 == 1 {
+        seats.push(None);
+    }
+    if seats.len() < 2 {
+        return (Vec::new(), seats.into_iter().next().and_then(|seat| seat))
+    }
+    let rotation = (round as usize - 1) % (seats.len() - 1);
+    seats[1..].rotate_right(rotation);
+
+    let mut pairs = Vec::new();
+    let mut bye = None;
+    let half = seats.len() / 2;
+    for i in 0..half {
+        match (seats[i].clone(), seats[seats.len() - 1 - i].clone()) {
+            (Some(player_1), Some(player_2)) => pairs.push((player_1, player_2)),
+            (Some(player), None) | (None, Some(player)) => bye = Some(player),
+            (None, None) => (),
+        }
+    }
+    (pairs, bye)
+}
+
+/// Swiss: working down the standings each player meets the highest ranked player they have
+/// not played yet, backing up a step whenever that leaves someone without a new opponent.
+/// With an odd number of players the lowest ranked player who can sit out without forcing a
+/// rematch and has not had a bye gets one. None if every pairing would need a rematch.
+pub fn swiss_pairs<P, B>(ranked: Vec<Address>, have_played: P, had_bye: B) -> Option<(Vec<(Address, Address)>, Option<Address>)>
+    where P: Fn(&Address, &Address) -> bool, B: Fn(&Address) -> bool
+{
+    if ranked.len() % 2 == 0 {
+        return pair_unplayed(&ranked, &have_played).map(|pairs| (pairs, None))
+    }
+
+    let mut candidates: Vec<usize> = (0..ranked.len()).rev().filter(|i| !had_bye(&ranked[*i])).collect();
+    // once everyone has had a bye it starts again from the bottom
+    if candidates.is_empty() {
+        candidates = (0..ranked.len()).rev().collect();
+    }
+    candidates.into_iter().filter_map(|i| {
+        let mut rest = ranked.clone();
+        let bye = rest.remove(i);
+        pair_unplayed(&rest, &have_played).map(|pairs| (pairs, Some(bye)))
+    }).next()
+}
+
+// The backtracking search can take exponentially many steps when most pairings are rematches,
+// so after MAX_PAIRING_STEPS it gives up and pairs greedily, allowing rematches where needed.
+fn pair_unplayed<F: Fn(&Address, &Address) -> bool>(players: &[Address], have_played: &F) -> Option<Vec<(Address, Address)>> {
+    let mut steps = 0;
+    match search_unplayed(players, have_played, &mut steps) {
+        None if steps > MAX_PAIRING_STEPS => Some(greedy_pairs(players, have_played)),
+        found => found,
+    }
+}
+
+fn search_unplayed<F: Fn(&Address, &Address) -> bool>(players: &[Address], have_played: &F, steps: &mut usize) -> Option<Vec<(Address, Address)>> {
+    let (player, rest) = match players.split_first() {
+        Some(split) => split,
+        None => return Some(Vec::new()),
+    };
+    for (i, opponent) in rest.iter().enumerate() {
+        *steps += 1;
+        if *steps > MAX_PAIRING_STEPS {
+            return None
+        }
+        if have_played(player, opponent) {
+            continue
+        }
+        let mut remaining = rest.to_vec();
+        remaining.remove(i);
+        if let Some(mut pairs) = search_unplayed(&remaining, have_played, steps) {
+            pairs.insert(0, (player.clone(), opponent.clone()));
+            return Some(pairs)
+        }
+    }
+    None
+}
+
+// each player in turn meets the highest ranked player left they have not played, or failing that the highest ranked
+fn greedy_pairs<F: Fn(&Address, &Address) -> bool>(players: &[Address], have_played: &F) -> Vec<(Address, Address)> {
+    let mut remaining = players.to_vec();
+    let mut pairs = Vec::new();
+    while remaining.len() >= 2 {
+        let player = remaining.remove(0);
+        let i = remaining.iter().position(|opponent| !have_played(&player, opponent)).unwrap_or(0);
+        let opponent = remaining.remove(i);
+        pairs.push((player, opponent));
+    }
+    pairs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn players(count: usize) -> Vec<Address> {
+        (0..count).map(|n| Address::from(format!("HcPlayer{}", n))).collect()
+    }
+
+    fn same_pair(pair: &(Address, Address), a: &Address, b: &Address) -> bool {
+        (pair.0 == *a && pair.1 == *b) || (pair.0 == *b && pair.1 == *a)
+    }
+
+    fn everyone_once(pairs: &[(Address, Address)], bye: &Option<Address>, players: &[Address]) -> bool {
+        let mut seen: Vec<&Address> = pairs.iter().flat_map(|(a, b)| vec![a, b]).chain(bye.iter()).collect();
+        seen.sort_by(|a, b| a.to_string().cmp(&b.to_string()));
+        seen.dedup();
+        seen.len() == players.len() && pairs.len() * 2 + bye.iter().count() == players.len()
+    }
+
+    #[test]
+    fn round_robin_meets_everyone_once() {
+        let players = players(4);
+        let mut pairs = Vec::new();
+        for round in 1..=3 {
+            let (round_pairs, bye) = round_robin_pairs(players.clone(), round);
+            assert!(everyone_once(&round_pairs, &bye, &players));
+            pairs.extend(round_pairs);
+        }
+        for (i, a) in players.iter().enumerate() {
+            for b in &players[i + 1..] {
+                assert_eq!(pairs.iter().filter(|pair| same_pair(pair, a, b)).count(), 1);
+            }
+        }
+    }
+
+    #[test]
+    fn round_robin_gives_everyone_one_bye_with_an_odd_field() {
+        let players = players(3);
+        let byes: Vec<Address> = (1..=3).map(|round| round_robin_pairs(players.clone(), round).1.unwrap()).collect();
+        for player in &players {
+            assert_eq!(byes.iter().filter(|bye| *bye == player).count(), 1);
+        }
+        assert_eq!(round_robin_pairs(players[..1].to_vec(), 1), (Vec::new(), Some(players[0].clone())));
+    }
+
+    #[test]
+    fn swiss_pairs_down_the_standings() {
+        let players = players(4);
+        let (pairs, bye) = swiss_pairs(players.clone(), |_, _| false, |_| false).unwrap();
+        assert_eq!(pairs, vec![(players[0].clone(), players[1].clone()), (players[2].clone(), players[3].clone())]);
+        assert_eq!(bye, None);
+    }
+
+    #[test]
+    fn swiss_avoids_rematches() {
+        let players = players(4);
+        let played = |a: &Address, b: &Address| same_pair(&(players[0].clone(), players[1].clone()), a, b);
+        let (pairs, _) = swiss_pairs(players.clone(), played, |_| false).unwrap();
+        assert_eq!(pairs, vec![(players[0].clone(), players[2].clone()), (players[1].clone(), players[3].clone())]);
+    }
+
+    #[test]
+    fn swiss_gives_the_bye_to_the_lowest_player_without_one() {
+        let players = players(3);
+        let (_, bye) = swiss_pairs(players.clone(), |_, _| false, |_| false).unwrap();
+        assert_eq!(bye, Some(players[2].clone()));
+        let (_, bye) = swiss_pairs(players.clone(), |_, _| false, |player| *player == players[2]).unwrap();
+        assert_eq!(bye, Some(players[1].clone()));
+    }
+
+    #[test]
+    fn swiss_refuses_when_only_rematches_are_left() {
+        let players = players(2);
+        assert_eq!(swiss_pairs(players, |_, _| true, |_| false), None);
+    }
+
+    #[test]
+    fn swiss_pairs_greedily_once_the_search_takes_too_long() {
+        // the last player has met everyone, which the search only finds out after trying every other pairing
+        let players = players(16);
+        let last = players[15].clone();
+        let played = |a: &Address, b: &Address| *a == last || *b == last;
+        let (pairs, bye) = swiss_pairs(players.clone(), played, |_| false).unwrap();
+        assert!(everyone_once(&pairs, &bye, &players));
+        assert_eq!(pairs.iter().filter(|pair| pair.0 == last || pair.1 == last).count(), 1);
+
+        // a small field is searched to the end and refused
+        let players = players[..4].to_vec();
+        let last = players[3].clone();
+        assert_eq!(swiss_pairs(players, |a, b| *a == last || *b == last, |_| false), None);
+    }
+}
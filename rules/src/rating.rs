@@ -0,0 +1,43 @@
+/// The rating of an agent before their first rated game
+pub const INITIAL_RATING: f64 = 1500.0;
+const K_FACTOR: f64 = 32.0;
+
+/// The new Elo rating of a player after scoring `score` against an opponent,
+/// 1 for a win, 0.5 for a draw and 0 for a loss
+pub fn elo(rating: f64, opponent_rating: f64, score: f64) -> f64 {
+    let expected = 1.0 / (1.0 + 10f64.powf((opponent_rating - rating) / 400.0));
+    rating + K_FACTOR * (score - expected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-9, "{} is not {}", actual, expected);
+    }
+
+    #[test]
+    fn evenly_matched_players_move_by_half_the_k_factor() {
+        assert_close(elo(INITIAL_RATING, INITIAL_RATING, 1.0), 1516.0);
+        assert_close(elo(INITIAL_RATING, INITIAL_RATING, 0.0), 1484.0);
+        assert_close(elo(INITIAL_RATING, INITIAL_RATING, 0.5), INITIAL_RATING);
+    }
+
+    #[test]
+    fn beating_a_stronger_player_gains_more() {
+        let upset = elo(1400.0, 1800.0, 1.0) - 1400.0;
+        let expected_win = elo(1800.0, 1400.0, 1.0) - 1800.0;
+        // 400 points apart the stronger player is expected to score 10/11
+        assert_close(upset, 32.0 * 10.0 / 11.0);
+        assert_close(expected_win, 32.0 / 11.0);
+    }
+
+    #[test]
+    fn ratings_are_only_exchanged() {
+        for &(rating_1, rating_2, score) in &[(1500.0, 1500.0, 1.0), (1620.0, 1380.0, 0.0), (1200.0, 2100.0, 0.5)] {
+            let total_after = elo(rating_1, rating_2, score) + elo(rating_2, rating_1, 1.0 - score);
+            assert_close(total_after, rating_1 + rating_2);
+        }
+    }
+}
@@ -0,0 +1,101 @@
+/// Seconds since the Unix epoch of a header time. Header times are ISO 8601 strings
+/// such as 2019-07-01T12:30:00+00:00 or 2019-07-01T12:30:00.5Z
+pub fn seconds_since_epoch(iso: &str) -> Result<u32, String> {
+    parse_seconds(iso).ok_or_else(|| format!("Could not read the time {}", iso))
+}
+
+fn parse_seconds(iso: &str) -> Option<u32> {
+    let number = |from: usize, to: usize| iso.get(from..to)
+        .filter(|digits| digits.chars().all(|c| c.is_ascii_digit()))
+        .and_then(|digits| digits.parse::<i64>().ok());
+    let separators_match = [(4, '-'), (7, '-'), (13, ':'), (16, ':')].iter()
+        .all(|(index, separator)| iso.chars().nth(*index) == Some(*separator));
+    if !separators_match || !(iso.chars().nth(10) == Some('T') || iso.chars().nth(10) == Some('t')) {
+        return None
+    }
+    let (year, month, day) = (number(0, 4)?, number(5, 7)?, number(8, 10)?);
+    let (hour, minute, second) = (number(11, 13)?, number(14, 16)?, number(17, 19)?);
+    if month < 1 || month > 12 || day < 1 || day > 31 || hour > 23 || minute > 59 || second > 60 {
+        return None
+    }
+
+    // skip any fraction of a second to find the offset from UTC
+    let zone = iso.get(19..)?.trim_start_matches(|c: char| c == '.' || c.is_ascii_digit());
+    let offset = match zone.chars().next()? {
+        'Z' | 'z' if zone.len() == 1 => 0,
+        sign @ '+' | sign @ '-' if zone.len() == 6 && zone.get(3..4) == Some(":") => {
+            let minutes = zone.get(1..3)?.parse::<i64>().ok()? * 60 + zone.get(4..6)?.parse::<i64>().ok()?;
+            if sign == '+' { minutes * 60 } else { -minutes * 60 }
+        },
+        _ => return None,
+    };
+
+    // days since 1970-01-01 in the proleptic Gregorian calendar
+    let shifted_year = if month <= 2 { year - 1 } else { year };
+    let era = shifted_year / 400;
+    let year_of_era = shifted_year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146_097 + day_of_era - 719_468;
+
+    let seconds = days * 86_400 + hour * 3_600 + minute * 60 + second - offset;
+    if seconds < 0 || seconds > i64::from(u32::max_value()) {
+        None
+    } else {
+        Some(seconds as u32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::seconds_since_epoch;
+
+    #[test]
+    fn reads_utc_times() {
+        assert_eq!(seconds_since_epoch("1970-01-01T00:00:00Z"), Ok(0));
+        assert_eq!(seconds_since_epoch("2019-07-01T12:30:00Z"), Ok(1_561_984_200));
+        assert_eq!(seconds_since_epoch("2019-07-01T12:30:00+00:00"), Ok(1_561_984_200));
+        assert_eq!(seconds_since_epoch("2019-12-31T23:59:59z"), Ok(1_577_836_799));
+    }
+
+    #[test]
+    fn applies_numeric_offsets() {
+        assert_eq!(seconds_since_epoch("2019-07-01T14:30:00+02:00"), Ok(1_561_984_200));
+        assert_eq!(seconds_since_epoch("2019-07-01T07:00:00-05:30"), Ok(1_561_984_200));
+    }
+
+    #[test]
+    fn ignores_fractions_of_a_second() {
+        assert_eq!(seconds_since_epoch("2019-07-01T12:30:00.5Z"), Ok(1_561_984_200));
+        assert_eq!(seconds_since_epoch("2019-07-01T12:30:00.123456+00:00"), Ok(1_561_984_200));
+    }
+
+    #[test]
+    fn counts_leap_days() {
+        assert_eq!(seconds_since_epoch("2020-02-29T00:00:00Z"), Ok(1_582_934_400));
+        // 2000 is a leap year, 2100 is not
+        assert_eq!(seconds_since_epoch("2000-03-01T00:00:00Z"), Ok(951_868_800));
+        let end_of_february = seconds_since_epoch("2100-02-28T00:00:00Z").unwrap();
+        assert_eq!(seconds_since_epoch("2100-03-01T00:00:00Z"), Ok(end_of_february + 86_400));
+    }
+
+    #[test]
+    fn rejects_malformed_times() {
+        for malformed in &[
+            "",
+            "not a time",
+            "2019-07-01",
+            "2019-07-01T12:30:00",
+            "2019-07-01 12:30:00Z",
+            "2019/07/01T12:30:00Z",
+            "2019-13-01T12:30:00Z",
+            "2019-07-01T25:30:00Z",
+            "2019-07-01T12:30:00+0200",
+            "2019-07-01T12:30:00Zjunk",
+            "+019-07-01T12:30:00Z",
+            "1969-12-31T23:59:59Z",
+        ] {
+            assert!(seconds_since_epoch(malformed).is_err(), "{} should not parse", malformed);
+        }
+    }
+}
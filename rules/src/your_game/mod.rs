@@ -6,12 +6,21 @@
 pub mod state;
 pub mod validation;
 pub mod moves;
+pub mod notation;
 
 pub use self::{
     state::{
         GameState,
+        Outcome,
+        coin_commitment,
+    },
+    validation::{
+        whose_turn,
     },
     moves::{
         MoveType,
     },
+    notation::{
+        VARIANT,
+    },
 };
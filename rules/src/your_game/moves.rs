@@ -0,0 +1,65 @@
+use holochain_json_api::{
+    error::JsonError, json::JsonString,
+};
+use holochain_persistence_api::cas::content::Address;
+
+/**
+ *
+ * The MoveType enum defines all the types of moves that are valid in your game and the
+ * data they carry. In Checkers you can move a piece (MovePiece) from a location to another location.
+ *
+ */
+
+#[derive(Clone, Debug, Serialize, Deserialize, DefaultJson, PartialEq)]
+pub enum MoveType {
+    // MOVE ENUM VARIENTS HERE
+    MoveToken{x: usize, y: usize, distance: usize},
+    CreateToken{distance: usize},
+    // HomeToken{}?
+    // the hash of a secret, the first step of the coin flip deciding who moves first
+    CommitCoin{commitment: Address},
+    // the secret behind the commitment, once both players have committed
+    RevealCoin{secret: String},
+    // end the game when the opponent has run out of time
+    ClaimTimeout,
+    // end the game when the opponent has stopped playing
+    ClaimAbandoned,
+    // ask the opponent to undo the last move(s) made by the requesting player
+    RequestTakeback,
+    // undo the moves the opponent asked to take back
+    AcceptTakeback,
+    // offer to end the game as a draw
+    OfferDraw,
+    // agree to the opponent's draw offer
+    AcceptDraw,
+    // call the game off before both players have moved
+    Abort,
+    // give up, the opponent wins
+    Resign,
+}
+
+impl MoveType {
+	/// True for the moves which move a token on the board
+	pub fn is_token_move(&self) -> bool {
+		match self {
+			MoveType::MoveToken{..} | MoveType::CreateToken{..} => true,
+			_ => false,
+		}
+	}
+
+	pub fn describe() -> Vec<MoveType> {
+		// SHOULD RETURN AN EXAMPLE OF EACH VARIENT
+		vec![MoveType::MoveToken{x: 3, y: 0, distance: 2},
+             MoveType::CreateToken{distance: 2},
+             MoveType::CommitCoin{commitment: "QmCommitmentHash".into()},
+             MoveType::RevealCoin{secret: "my secret".into()},
+             MoveType::ClaimTimeout,
+             MoveType::ClaimAbandoned,
+             MoveType::RequestTakeback,
+             MoveType::AcceptTakeback,
+             MoveType::OfferDraw,
+             MoveType::AcceptDraw,
+             MoveType::Abort,
+             MoveType::Resign,]
+	}
+}
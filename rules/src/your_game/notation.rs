@@ -0,0 +1,443 @@
+use holochain_persistence_api::cas::content::{Address, AddressableContent};
+use holochain_core_types::entry::Entry;
+
+use crate::game::{Game, TimeControl};
+use crate::game_move::Move;
+use super::{GameState, MoveType, Outcome};
+use super::state::{increment_location, Token};
+use super::validation::is_rosette;
+
+/**
+ *
+ * A plain text notation for complete games of Ur, in the spirit of PGN:
+ *
+ *     [Game "QmGame"]
+ *     [Player1 "HcAgentOne"]
+ *     [Player2 "HcAgentTwo"]
+ *     [Date "1560000000"]
+ *     [Variant "Finkel"]
+ *     [FirstPlayer "P2"]
+ *     [TimeControl "Fischer 300+5"]
+ *     [InactivityWindow "604800"]
+ *     [Result "0-1"]
+ *     [Termination "resignation"]
+ *
+ *     1. P2 4 0-4* @1560000010
+ *     2. P2 2 4-6 @1560000020
+ *     3. P1 3 0-3 @1560000031
+ *     4. P1 resign @1560000040
+ *
+ * Every numbered line is one move: who made it, what they did and when. A token move
+ * is the roll followed by the squares along the player's path it moved from and to,
+ * where 0 is off the board and 15 is home. It is marked with `*` when it lands on a
+ * rosette, `x` when it lands on an opponent's token and `^` when the token goes home.
+ *
+ */
+
+/// The rule set these games are played with
+pub const VARIANT: &str = "Finkel";
+// the index on a player's path of the square past the end of the board
+const HOME: usize = 15;
+
+/// Write a game and its moves out as notation
+pub fn to_notation(game_address: &Address, game: &Game, moves: &[Move]) -> String {
+    let final_state = moves.iter().fold(GameState::initial(), |state, game_move| state.evolve(game.clone(), game_move));
+
+    let mut notation = String::new();
+    for (tag, value) in headers(game_address, game, &final_state) {
+        notation.push_str(&format!("[{} \"{}\"]\n", tag, value));
+    }
+    notation.push('\n');
+
+    let mut state = GameState::initial();
+    for (index, game_move) in moves.iter().enumerate() {
+        notation.push_str(&format!("{}. {} @{}\n", index + 1, move_text(game, &state, game_move), game_move.timestamp));
+        state = state.evolve(game.clone(), game_move);
+    }
+    notation
+}
+
+/// A single move as it is written in notation, e.g. `P1 3 5-8*`. `state` is the state before the move.
+pub fn move_text(game: &Game, state: &GameState, game_move: &Move) -> String {
+    format!("P{} {}", player_number(game, &game_move.author), describe_move(game, state, game_move))
+}
+
+/// Read a game back from notation, replaying every move through validation and `evolve`.
+/// Fails on the first move which breaks the rules or if the headers disagree with the moves.
+pub fn from_notation(notation: &str) -> Result<(Game, Vec<Move>, GameState), String> {
+    let mut headers: Vec<(String, String)> = Vec::new();
+    let mut move_lines = Vec::new();
+    for line in notation.lines().map(|line| line.trim()).filter(|line| !line.is_empty()) {
+        if line.starts_with('[') {
+            headers.push(parse_header(line)?);
+        } else {
+            move_lines.push(line);
+        }
+    }
+    let header = |tag: &str| {
+        headers.iter()
+            .find(|(header_tag, _)| header_tag == tag)
+            .map(|(_, value)| value.clone())
+            .ok_or(format!("Missing the {} header", tag))
+    };
+
+    let player_1: Address = header("Player1")?.into();
+    let player_2: Address = header("Player2")?.into();
+    let first_player = match header("FirstPlayer")?.as_str() {
+        "P1" => Some(player_1.clone()),
+        "P2" => Some(player_2.clone()),
+        "CoinFlip" => None,
+        other => return Err(format!("Unknown first player {}", other)),
+    };
+    let game = Game {
+        player_1,
+        player_2,
+        created_at: parse_number(&header("Date")?)?,
+        first_player,
+        time_control: parse_time_control(&header("TimeControl")?)?,
+        inactivity_window: parse_number(&header("InactivityWindow")?)?,
+    };
+    let game_address = Entry::App("game".into(), game.clone().into()).address();
+    if let Ok(expected) = header("Game") {
+        if expected != game_address.to_string() {
+            return Err("The Game header does not match the players and settings of the game".into())
+        }
+    }
+
+    let mut state = GameState::initial();
+    let mut moves = Vec::new();
+    let mut previous_move = game_address.clone();
+    for (index, line) in move_lines.into_iter().enumerate() {
+        let game_move = parse_move(line, index + 1, &game, &game_address, &previous_move)?;
+        game_move.is_valid(game.clone(), state.clone())
+            .map_err(|err| format!("Move {} is not valid: {}", index + 1, err))?;
+        state = state.evolve(game.clone(), &game_move);
+        previous_move = Entry::App("move".into(), game_move.clone().into()).address();
+        moves.push(game_move);
+    }
+
+    if let Ok(result) = header("Result") {
+        if result != result_text(&game, &state) {
+            return Err("The Result header does not match the moves".into())
+        }
+    }
+    Ok((game, moves, state))
+}
+
+fn headers(game_address: &Address, game: &Game, state: &GameState) -> Vec<(&'static str, String)> {
+    let first_player = match &game.first_player {
+        Some(first_player) => format!("P{}", player_number(game, first_player)),
+        None => "CoinFlip".into(),
+    };
+    let mut headers = vec![
+        ("Game", game_address.to_string()),
+        ("Player1", game.player_1.to_string()),
+        ("Player2", game.player_2.to_string()),
+        ("Date", game.created_at.to_string()),
+        ("Variant", VARIANT.into()),
+        ("FirstPlayer", first_player),
+        ("TimeControl", time_control_text(&game.time_control)),
+        ("InactivityWindow", game.inactivity_window.to_string()),
+        ("Result", result_text(game, state)),
+    ];
+    if let Some(outcome) = &state.outcome {
+        headers.push(("Termination", termination_text(outcome).into()));
+    }
+    headers
+}
+
+fn result_text(game: &Game, state: &GameState) -> String {
+    match &state.outcome {
+        None => "*".into(),
+        Some(Outcome::Draw) => "1/2-1/2".into(),
+        Some(Outcome::Aborted) => "aborted".into(),
+        Some(outcome) => {
+            if outcome.winner() == Some(game.player_1.clone()) { "1-0".into() } else { "0-1".into() }
+        },
+    }
+}
+
+fn termination_text(outcome: &Outcome) -> &'static str {
+    match outcome {
+        Outcome::Win{..} => "all tokens home",
+        Outcome::Timeout{..} => "timeout",
+        Outcome::Abandoned{..} => "abandoned",
+        Outcome::Resigned{..} => "resignation",
+        Outcome::Draw => "agreement",
+        Outcome::Aborted => "aborted",
+    }
+}
+
+fn time_control_text(time_control: &TimeControl) -> String {
+    match time_control {
+        TimeControl::Unlimited => "Unlimited".into(),
+        TimeControl::Correspondence{days_per_move} => format!("Correspondence {}", days_per_move),
+        TimeControl::Fischer{initial_seconds, increment_seconds} => format!("Fischer {}+{}", initial_seconds, increment_seconds),
+    }
+}
+
+fn parse_time_control(text: &str) -> Result<TimeControl, String> {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    match words.as_slice() {
+        ["Unlimited"] => Ok(TimeControl::Unlimited),
+        ["Correspondence", days] => Ok(TimeControl::Correspondence{days_per_move: parse_number(days)?}),
+        ["Fischer", clock] => {
+            let parts: Vec<&str> = clock.splitn(2, '+').collect();
+            match parts.as_slice() {
+                [initial, increment] => Ok(TimeControl::Fischer{
+                    initial_seconds: parse_number(initial)?,
+                    increment_seconds: parse_number(increment)?,
+                }),
+                _ => Err(format!("Could not read the Fischer clock {}", clock)),
+            }
+        },
+        _ => Err(format!("Unknown time control {}", text)),
+    }
+}
+
+fn parse_header(line: &str) -> Result<(String, String), String> {
+    let inner = line.trim_start_matches('[').trim_end_matches(']');
+    let parts: Vec<&str> = inner.splitn(2, ' ').collect();
+    match parts.as_slice() {
+        [tag, value] => Ok((tag.to_string(), value.trim().trim_matches('"').to_string())),
+        _ => Err(format!("Could not read the header {}", line)),
+    }
+}
+
+// A move line looks like `12. P1 3 5-8* @1560000000`
+fn parse_move(line: &str, number: usize, game: &Game, game_address: &Address, previous_move: &Address) -> Result<Move, String> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let unreadable = || format!("Could not read move {}: {}", number, line);
+    let (number_word, rest) = words.split_first().ok_or_else(unreadable)?;
+    let (player_word, rest) = rest.split_first().ok_or_else(unreadable)?;
+    let (timestamp_word, move_words) = rest.split_last().ok_or_else(unreadable)?;
+    if move_words.is_empty() {
+        return Err(unreadable())
+    }
+    if *number_word != format!("{}.", number) {
+        return Err(format!("Expected move {} but found {}", number, number_word))
+    }
+    let (author, player) = match *player_word {
+        "P1" => (game.player_1.clone(), 1),
+        "P2" => (game.player_2.clone(), 2),
+        other => return Err(format!("Unknown player {} in move {}", other, number)),
+    };
+    if !timestamp_word.starts_with('@') {
+        return Err(format!("Move {} has no timestamp", number))
+    }
+
+    Ok(Move {
+        game: game_address.clone(),
+        author,
+        move_type: parse_move_type(move_words, player)?,
+        previous_move: previous_move.clone(),
+        timestamp: parse_number(timestamp_word.trim_start_matches('@'))?,
+    })
+}
+
+fn parse_move_type(words: &[&str], player: usize) -> Result<MoveType, String> {
+    let (first, rest) = words.split_first().ok_or("A move must say what was played".to_string())?;
+    match (*first, rest) {
+        ("commit", [commitment]) => Ok(MoveType::CommitCoin{commitment: (*commitment).into()}),
+        ("reveal", secret) if !secret.is_empty() => Ok(MoveType::RevealCoin{secret: secret.join(" ")}),
+        ("claim-timeout", []) => Ok(MoveType::ClaimTimeout),
+        ("claim-abandoned", []) => Ok(MoveType::ClaimAbandoned),
+        ("request-takeback", []) => Ok(MoveType::RequestTakeback),
+        ("accept-takeback", []) => Ok(MoveType::AcceptTakeback),
+        ("offer-draw", []) => Ok(MoveType::OfferDraw),
+        ("accept-draw", []) => Ok(MoveType::AcceptDraw),
+        ("abort", []) => Ok(MoveType::Abort),
+        ("resign", []) => Ok(MoveType::Resign),
+        (roll, [squares_word]) => {
+            let distance: usize = parse_number(roll)?;
+            let squares: Vec<&str> = squares_word
+                .trim_end_matches(|c| c == '*' || c == 'x' || c == '^')
+                .splitn(2, '-')
+                .collect();
+            let (from, to): (usize, usize) = match squares.as_slice() {
+                [from, to] => (parse_number(from)?, parse_number(to)?),
+                _ => return Err(format!("Could not read the squares {}", squares_word)),
+            };
+            if from + distance != to {
+                return Err(format!("Moving {} from {} does not end on {}", distance, from, to))
+            }
+            if from == 0 {
+                Ok(MoveType::CreateToken{distance})
+            } else {
+                let (x, y) = path_square(from, player);
+                Ok(MoveType::MoveToken{x, y, distance})
+            }
+        },
+        _ => Err(format!("Could not read the move {}", words.join(" "))),
+    }
+}
+
+fn describe_move(game: &Game, state: &GameState, game_move: &Move) -> String {
+    let player = player_number(game, &game_move.author);
+    match &game_move.move_type {
+        MoveType::CreateToken{distance} => describe_token_move(state, player, 0, *distance),
+        MoveType::MoveToken{x, y, distance} => {
+            let from = path_index(*x, *y, player).unwrap_or(0);
+            describe_token_move(state, player, from, *distance)
+        },
+        MoveType::CommitCoin{commitment} => format!("commit {}", commitment),
+        MoveType::RevealCoin{secret} => format!("reveal {}", secret),
+        MoveType::ClaimTimeout => "claim-timeout".into(),
+        MoveType::ClaimAbandoned => "claim-abandoned".into(),
+        MoveType::RequestTakeback => "request-takeback".into(),
+        MoveType::AcceptTakeback => "accept-takeback".into(),
+        MoveType::OfferDraw => "offer-draw".into(),
+        MoveType::AcceptDraw => "accept-draw".into(),
+        MoveType::Abort => "abort".into(),
+        MoveType::Resign => "resign".into(),
+    }
+}
+
+fn describe_token_move(state: &GameState, player: usize, from: usize, distance: usize) -> String {
+    let to = from + distance;
+    let mut text = format!("{} {}-{}", distance, from, to);
+    if to == HOME {
+        text.push('^');
+    } else {
+        let (x, y) = path_square(to, player);
+        let opponent_tokens = if player == 1 { &state.p2_tokens } else { &state.p1_tokens };
+        if is_rosette(x, y) {
+            text.push('*');
+        } else if opponent_tokens.contains(&Token{x, y}) {
+            text.push('x');
+        }
+    }
+    text
+}
+
+// the square a player's token is on after moving `index` squares onto the board
+fn path_square(index: usize, player: usize) -> (usize, usize) {
+    if player == 1 {
+        increment_location(4, 0, index, 1)
+    } else {
+        increment_location(4, 2, index, 2)
+    }
+}
+
+fn path_index(x: usize, y: usize, player: usize) -> Option<usize> {
+    (0..=HOME).find(|index| path_square(*index, player) == (x, y))
+}
+
+fn player_number(game: &Game, player: &Address) -> usize {
+    if *player == game.player_1 { 1 } else { 2 }
+}
+
+fn parse_number<T: std::str::FromStr>(text: &str) -> Result<T, String> {
+    text.parse().map_err(|_| format!("{} is not a number", text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::DEFAULT_INACTIVITY_WINDOW;
+
+    fn game() -> Game {
+        Game {
+            player_1: "HcPlayerOne".into(),
+            player_2: "HcPlayerTwo".into(),
+            created_at: 1_560_000_000,
+            first_player: Some("HcPlayerOne".into()),
+            time_control: TimeControl::Fischer{initial_seconds: 300, increment_seconds: 5},
+            inactivity_window: DEFAULT_INACTIVITY_WINDOW,
+        }
+    }
+
+    // moves chained to each other the way make_move commits them
+    fn play(game: &Game, moves: Vec<(&Address, MoveType)>) -> Vec<Move> {
+        let game_address = Entry::App("game".into(), game.clone().into()).address();
+        let mut previous_move = game_address.clone();
+        moves.into_iter().enumerate().map(|(index, (author, move_type))| {
+            let game_move = Move {
+                game: game_address.clone(),
+                author: author.clone(),
+                move_type,
+                previous_move: previous_move.clone(),
+                timestamp: game.created_at + 10 * (index as u32 + 1),
+            };
+            previous_move = Entry::App("move".into(), game_move.clone().into()).address();
+            game_move
+        }).collect()
+    }
+
+    #[test]
+    fn replays_an_exported_game() {
+        let game = game();
+        let (p1, p2) = (game.player_1.clone(), game.player_2.clone());
+        let moves = play(&game, vec![
+            (&p1, MoveType::CreateToken{distance: 2}),
+            (&p2, MoveType::CreateToken{distance: 3}),
+            (&p1, MoveType::MoveToken{x: 2, y: 0, distance: 1}),
+            (&p2, MoveType::OfferDraw),
+            (&p1, MoveType::AcceptDraw),
+        ]);
+        let game_address = Entry::App("game".into(), game.clone().into()).address();
+        let notation = to_notation(&game_address, &game, &moves);
+
+        let (replayed_game, replayed_moves, state) = from_notation(&notation).unwrap();
+        assert_eq!(Entry::App("game".into(), replayed_game.into()).address(), game_address);
+        assert_eq!(replayed_moves, moves);
+        assert_eq!(state.outcome, Some(Outcome::Draw));
+        assert!(notation.contains("[Result \"1/2-1/2\"]"));
+    }
+
+    #[test]
+    fn reads_every_kind_of_move() {
+        assert_eq!(parse_move_type(&["commit", "QmCommitment"], 1), Ok(MoveType::CommitCoin{commitment: "QmCommitment".into()}));
+        assert_eq!(parse_move_type(&["reveal", "two", "words"], 1), Ok(MoveType::RevealCoin{secret: "two words".into()}));
+        assert_eq!(parse_move_type(&["resign"], 2), Ok(MoveType::Resign));
+        assert_eq!(parse_move_type(&["2", "0-2"], 1), Ok(MoveType::CreateToken{distance: 2}));
+        assert_eq!(parse_move_type(&["1", "2-3x"], 1), Ok(MoveType::MoveToken{x: 2, y: 0, distance: 1}));
+    }
+
+    #[test]
+    fn rejects_malformed_moves() {
+        let game = game();
+        let game_address = Entry::App("game".into(), game.clone().into()).address();
+        for line in &[
+            "",
+            "1.",
+            "1. P1",
+            "1. P1 @1560000010",
+            "2. P1 resign @1560000010",
+            "1. P3 resign @1560000010",
+            "1. P1 resign 1560000010",
+            "1. P1 resign @soon",
+        ] {
+            assert!(parse_move(line, 1, &game, &game_address, &game_address).is_err(), "{} should not parse", line);
+        }
+        for words in &[
+            vec![],
+            vec!["commit"],
+            vec!["commit", "QmOne", "QmTwo"],
+            vec!["reveal"],
+            vec!["resign", "now"],
+            vec!["2", "0-3"],
+            vec!["2", "02"],
+            vec!["two", "0-2"],
+            vec!["castle"],
+        ] {
+            assert!(parse_move_type(words, 1).is_err(), "{:?} should not parse", words);
+        }
+    }
+
+    #[test]
+    fn rejects_notation_which_breaks_the_rules() {
+        let game = game();
+        let (p1, p2) = (game.player_1.clone(), game.player_2.clone());
+        let moves = play(&game, vec![(&p1, MoveType::CreateToken{distance: 2}), (&p2, MoveType::Resign)]);
+        let game_address = Entry::App("game".into(), game.clone().into()).address();
+        let notation = to_notation(&game_address, &game, &moves);
+
+        // player 2 can't move first
+        assert!(from_notation(&notation.replace("1. P1", "1. P2")).is_err());
+        // the result must match the moves
+        assert!(from_notation(&notation.replace("[Result \"1-0\"]", "[Result \"0-1\"]")).is_err());
+        // every header is needed to rebuild the game
+        assert!(from_notation(&notation.replace("[Player2", "[Opponent")).is_err());
+    }
+}
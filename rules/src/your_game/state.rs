@@ -0,0 +1,519 @@
+use holochain_json_api::{
+    error::JsonError, json::{JsonString, RawString},
+};
+use holochain_persistence_api::cas::content::{Address, AddressableContent};
+use holochain_core_types::entry::Entry;
+
+use crate::game_move::Move;
+use crate::game::Game;
+use super::MoveType;
+use super::validation::waiting_on;
+
+
+/**
+ *
+ * As a game author you get to decide what the State object of your game looks like.
+ * Most of the time you want it to include all of the previous moves as well.
+ *
+ * To customize the game state implement your own GameState struct. This must have a function called `initial()`
+ * which returns the initial state.
+ *
+ */
+
+
+#[derive(Clone, Debug, Serialize, Deserialize, DefaultJson)]
+pub struct GameState {
+    pub moves: Vec<Move>,
+    pub p1_tokens: Vec<Token>,
+    pub p1_home: usize,
+    pub p2_tokens: Vec<Token>,
+    pub p2_home: usize,
+    pub outcome: Option<Outcome>,
+    // seconds left on each player's clock, None until they first move or if there is no clock
+    pub p1_clock: Option<u32>,
+    pub p2_clock: Option<u32>,
+    // only filled in when the state is requested at a given time
+    pub inactive: Option<Inactivity>,
+    // indices into `moves` of the token moves which have been taken back
+    pub taken_back: Vec<usize>,
+    // the player waiting for their opponent to accept a takeback
+    pub pending_takeback: Option<Address>,
+    // the player waiting for their opponent to accept a draw
+    pub draw_offer: Option<Address>,
+    // who won the coin flip, for games which don't name a first player
+    pub first_player: Option<Address>,
+    // Implement your own game state
+    // May be helpful to split this into state for each player
+}
+
+/// How a game ended. A game with no outcome is still in progress.
+#[derive(Clone, Debug, Serialize, Deserialize, DefaultJson, PartialEq)]
+pub enum Outcome {
+    // all of the winner's tokens are home
+    Win{winner: Address},
+    // the loser ran out of time
+    Timeout{winner: Address},
+    // the loser stopped playing
+    Abandoned{winner: Address},
+    // the loser gave up
+    Resigned{winner: Address},
+    // both players agreed to a draw
+    Draw,
+    // called off before both players had moved
+    Aborted,
+}
+
+/// The player who is to move has not done so for a while
+#[derive(Clone, Debug, Serialize, Deserialize, DefaultJson, PartialEq)]
+pub struct Inactivity {
+    pub player: Address,
+    pub days: u32,
+    // whether the other player may now claim the game
+    pub can_claim: bool,
+}
+
+impl Outcome {
+    pub fn winner(&self) -> Option<Address> {
+        match self {
+            Outcome::Win{winner} => Some(winner.clone()),
+            Outcome::Timeout{winner} => Some(winner.clone()),
+            Outcome::Abandoned{winner} => Some(winner.clone()),
+            Outcome::Resigned{winner} => Some(winner.clone()),
+            Outcome::Draw | Outcome::Aborted => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, DefaultJson, PartialEq)]
+pub struct Token {
+    pub x: usize,
+    pub y: usize,
+}
+
+impl GameState {
+    pub fn initial() -> Self {
+        // return an initial state of a game
+        Self{
+            moves: Vec::new(),
+            p1_tokens: Vec::new(),
+            p1_home: 0,
+            p2_tokens: Vec::new(),
+            p2_home: 0,
+            outcome: None,
+            p1_clock: None,
+            p2_clock: None,
+            inactive: None,
+            taken_back: Vec::new(),
+            pending_takeback: None,
+            draw_offer: None,
+            first_player: None,
+        }
+    }
+
+    /// When anything last happened in the game
+    pub fn last_activity(&self, game: &Game) -> u32 {
+        self.moves.last()
+            .map(|last_move| last_move.timestamp)
+            .unwrap_or(game.created_at)
+    }
+
+    /// Note how long the player the game is waiting on has been inactive at the time `now`
+    pub fn with_inactivity(mut self, game: &Game, now: u32) -> Self {
+        let idle = now.saturating_sub(self.last_activity(game));
+        self.inactive = waiting_on(game, &self).and_then(|player| {
+            let days = idle / (24 * 60 * 60);
+            if days > 0 || idle > game.inactivity_window {
+                Some(Inactivity {
+                    player,
+                    days,
+                    can_claim: idle > game.inactivity_window,
+                })
+            } else {
+                None
+            }
+        });
+        self
+    }
+
+    /// The moves which moved a token and were not taken back, these are what decide whose turn it is
+    pub fn token_moves(&self) -> Vec<&Move> {
+        self.token_move_indices().into_iter().map(|index| &self.moves[index]).collect()
+    }
+
+    fn token_move_indices(&self) -> Vec<usize> {
+        self.moves.iter()
+            .enumerate()
+            .filter(|(index, game_move)| game_move.move_type.is_token_move() && !self.taken_back.contains(index))
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    /// Indices of the token moves a player would take back, i.e. their most recent
+    /// unbroken run of token moves. Empty if the opponent has moved since.
+    pub fn takeback_indices(&self, player: &Address) -> Vec<usize> {
+        self.token_move_indices()
+            .into_iter()
+            .rev()
+            .take_while(|index| self.moves[*index].author == *player)
+            .collect()
+    }
+
+    /// The player who makes the first token move, None while the coin flip is undecided
+    pub fn first_player(&self, game: &Game) -> Option<Address> {
+        game.first_player.clone().or(self.first_player.clone())
+    }
+
+    /// The commitment a player made at the start of the coin flip
+    pub fn coin_commitment(&self, player: &Address) -> Option<Address> {
+        self.moves.iter()
+            .filter(|game_move| game_move.author == *player)
+            .find_map(|game_move| match &game_move.move_type {
+                MoveType::CommitCoin{commitment} => Some(commitment.clone()),
+                _ => None,
+            })
+    }
+
+    /// The secret a player revealed to finish the coin flip
+    pub fn coin_secret(&self, player: &Address) -> Option<String> {
+        self.moves.iter()
+            .filter(|game_move| game_move.author == *player)
+            .find_map(|game_move| match &game_move.move_type {
+                MoveType::RevealCoin{secret} => Some(secret.clone()),
+                _ => None,
+            })
+    }
+
+    /// The player the coin flip is waiting on, having committed or revealed less than their opponent
+    pub fn coin_flip_waiting_on(&self, game: &Game) -> Option<Address> {
+        if self.is_finished() || self.first_player(game).is_some() {
+            return None
+        }
+        let steps_taken = |player: &Address| {
+            self.coin_commitment(player).iter().count() + self.coin_secret(player).iter().count()
+        };
+        let (p1_steps, p2_steps) = (steps_taken(&game.player_1), steps_taken(&game.player_2));
+        if p1_steps < p2_steps {
+            Some(game.player_1.clone())
+        } else if p2_steps < p1_steps {
+            Some(game.player_2.clone())
+        } else {
+            None
+        }
+    }
+
+    /// When the player who is to move started thinking
+    pub fn turn_started_at(&self, game: &Game) -> u32 {
+        self.token_moves().last()
+            .map(|last_move| last_move.timestamp)
+            .unwrap_or(game.created_at)
+    }
+
+    /// Seconds left on a player's clock at the start of their turn, None if there is no clock
+    pub fn clock(&self, game: &Game, player: &Address) -> Option<u32> {
+        let clock = if *player == game.player_1 { self.p1_clock } else { self.p2_clock };
+        clock.or(game.time_control.initial_clock())
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.outcome.is_some()
+    }
+
+    pub fn winner(&self) -> Option<Address> {
+        self.outcome.as_ref().and_then(|outcome| outcome.winner())
+    }
+
+    pub fn render(&self) -> String {
+        // <<DEVCAMP>> return a pretty formatting string representation
+        "".to_string()
+    }
+
+    pub fn evolve(&self, game: Game, next_move: &Move) -> GameState {
+        // <<DEVCAMP>>
+        // given a current state, a game and a move, compute the next state
+        // You can assume all moves are valid
+
+        let mut moves = self.moves.clone();
+        let mut p1_tokens = self.p1_tokens.clone();
+        let mut p1_home = self.p1_home.clone();
+        let mut p2_tokens = self.p2_tokens.clone();
+        let mut p2_home = self.p2_home.clone();
+        let mut outcome = self.outcome.clone();
+        let mut p1_clock = self.p1_clock.clone();
+        let mut p2_clock = self.p2_clock.clone();
+        let mut taken_back = self.taken_back.clone();
+        let mut pending_takeback = self.pending_takeback.clone();
+        let mut draw_offer = self.draw_offer.clone();
+        let mut first_player = self.first_player.clone();
+
+        // moving a token stops the mover's clock
+        if next_move.move_type.is_token_move() {
+            let elapsed = next_move.timestamp.saturating_sub(self.turn_started_at(&game));
+            let clock = self.clock(&game, &next_move.author)
+                .and_then(|remaining| game.time_control.after_move(remaining, elapsed));
+            if game.player_1 == next_move.author {
+                p1_clock = clock;
+            } else {
+                p2_clock = clock;
+            }
+            // moving on means any takeback request or draw offer has been turned down
+            pending_takeback = None;
+            draw_offer = None;
+        }
+
+        // add new move to the list of all moves
+        moves.push(next_move.clone());
+
+        // update state according to move
+        match next_move.move_type {
+            MoveType::MoveToken{x, y, distance} => {
+                if is_homing(x, y, distance) {
+                    // if token is going home
+                    if game.player_1 == next_move.author {
+                        p1_home += 1;
+                    } else {
+                        p2_home += 1;
+                    }
+                } else {
+                    // else token is moving
+                    if game.player_1 == next_move.author {
+                        // remove token at old location
+                        // p1_tokens.retain(|&token| (token.x, token.y) != (x,y));
+                        p1_tokens.retain(|token| (token.x, token.y) != (x,y));
+                        // add token at new location
+                        let (new_x, new_y) = increment_location(x, y, distance, 1);
+                        p1_tokens.push(Token{x: new_x, y: new_y});
+                    } else {
+                        // remove token at old location
+                        // p2_tokens.retain(|&token| (token.x, token.y) != (x,y));
+                        p2_tokens.retain(|token| (token.x, token.y) != (x,y));
+                        // add token at new location
+                        let (new_x, new_y) = increment_location(x, y, distance, 2);
+                        p2_tokens.push(Token{x: new_x, y: new_y});
+                    }
+                }
+            },
+            MoveType::CreateToken{distance} => {
+                if game.player_1 == next_move.author {
+                    let dest = increment_location(4, 0, distance, 1);
+                    p1_tokens.push(Token{x: dest.0, y: dest.1});
+                } else {
+                    let dest = increment_location(4, 2, distance, 2);
+                    p2_tokens.push(Token{x: dest.0, y: dest.1}); // FIXME not sure if this does anything because it's inside a different block than p2_tokens declaration
+                }
+            },
+            MoveType::CommitCoin{..} => (),
+            MoveType::RevealCoin{..} => {
+                // the coin lands once both secrets are known
+                let state = GameState{moves: moves.clone(), ..self.clone()};
+                if let (Some(secret_1), Some(secret_2)) = (state.coin_secret(&game.player_1), state.coin_secret(&game.player_2)) {
+                    first_player = Some(coin_flip_winner(&game, &secret_1, &secret_2));
+                }
+            },
+            MoveType::ClaimTimeout => {
+                outcome = Some(Outcome::Timeout{winner: next_move.author.clone()});
+            },
+            MoveType::ClaimAbandoned => {
+                outcome = Some(Outcome::Abandoned{winner: next_move.author.clone()});
+            },
+            MoveType::RequestTakeback => {
+                pending_takeback = Some(next_move.author.clone());
+            },
+            MoveType::AcceptTakeback => {
+                if let Some(requester) = pending_takeback.take() {
+                    taken_back.extend(self.takeback_indices(&requester));
+                    // the moves stay in the list but the board is rebuilt without them
+                    let rewound = moves.iter()
+                        .enumerate()
+                        .filter(|(index, game_move)| game_move.move_type.is_token_move() && !taken_back.contains(index))
+                        .fold(GameState::initial(), |state, (_, game_move)| state.evolve(game.clone(), game_move));
+                    p1_tokens = rewound.p1_tokens;
+                    p1_home = rewound.p1_home;
+                    p2_tokens = rewound.p2_tokens;
+                    p2_home = rewound.p2_home;
+                }
+            },
+            MoveType::OfferDraw => {
+                draw_offer = Some(next_move.author.clone());
+            },
+            MoveType::AcceptDraw => {
+                outcome = Some(Outcome::Draw);
+            },
+            MoveType::Abort => {
+                outcome = Some(Outcome::Aborted);
+            },
+            MoveType::Resign => {
+                let winner = if game.player_1 == next_move.author {
+                    game.player_2.clone()
+                } else {
+                    game.player_1.clone()
+                };
+                outcome = Some(Outcome::Resigned{winner});
+            },
+        }
+
+        // the first player with all their tokens home wins
+        if outcome.is_none() {
+            if p1_home == 7 {
+                outcome = Some(Outcome::Win{winner: game.player_1.clone()});
+            } else if p2_home == 7 {
+                outcome = Some(Outcome::Win{winner: game.player_2.clone()});
+            }
+        }
+
+        GameState {
+            moves,
+            p1_tokens: p1_tokens,
+            p1_home: p1_home,
+            p2_tokens: p2_tokens,
+            p2_home: p2_home,
+            outcome,
+            p1_clock,
+            p2_clock,
+            inactive: None,
+            taken_back,
+            pending_takeback,
+            draw_offer,
+            first_player,
+        }
+    }
+
+}
+
+/// The value a player commits to before revealing their secret
+pub fn coin_commitment(secret: &str) -> Address {
+    Entry::App("coin_secret".into(), RawString::from(secret).into()).address()
+}
+
+// Neither player can steer the result because each committed to their secret
+// before seeing the other's. The lowest bit of the hash digest picks the winner,
+// the base58 text of the address is not evenly spread over its last character.
+fn coin_flip_winner(game: &Game, secret_1: &str, secret_2: &str) -> Address {
+    let combined = coin_commitment(&format!("{}{}", secret_1, secret_2));
+    let last_byte = from_base58(&combined.to_string())
+        .and_then(|bytes| bytes.last().cloned())
+        .unwrap_or(0);
+    if last_byte & 1 == 0 {
+        game.player_1.clone()
+    } else {
+        game.player_2.clone()
+    }
+}
+
+const BASE58_ALPHABET: &[u8] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+// the bytes of a base58 string such as an address, None if it isn't valid base58
+fn from_base58(text: &str) -> Option<Vec<u8>> {
+    // little endian while the digits are added
+    let mut bytes: Vec<u8> = Vec::new();
+    for digit in text.bytes() {
+        let mut carry = BASE58_ALPHABET.iter().position(|&letter| letter == digit)? as u32;
+        for byte in bytes.iter_mut() {
+            carry += u32::from(*byte) * 58;
+            *byte = carry as u8;
+            carry >>= 8;
+        }
+        while carry > 0 {
+            bytes.push(carry as u8);
+            carry >>= 8;
+        }
+    }
+    // each leading 1 stands for a zero byte
+    bytes.extend(text.bytes().take_while(|&digit| digit == b'1').map(|_| 0));
+    bytes.reverse();
+    Some(bytes)
+}
+
+// takes token location, distance, and player and returns the location where a token at the
+// given location will land if moved the given distance by the given player.
+pub fn increment_location(x: usize, y: usize, distance: usize, player: usize) -> (usize, usize) {
+
+    let mut location = (x, y);
+
+    fn step(x: usize, y: usize, player: usize) -> (usize, usize) {
+        match (x, y) {
+            // merge
+            (0,0) | (0,2) => (0,1),
+            //split
+            (7,1) => {
+                if player == 1 {
+                    (7,0)
+                } else {
+                    (7,2)
+                }
+            },
+            // middle row
+            (_,1) => (x+1,y),
+            // outside rows (only thing left)
+            (_,_) => (x-1,y),
+        }
+    }
+
+    for _n in 1..=distance {
+        location = step(location.0, location.1, player);
+    }
+
+    return location;
+}
+
+// test whether a token moved a given distance from a given location will go home.
+pub fn is_homing(x: usize, y: usize, distance: usize) -> bool {
+    // player doesn't matter when calculating whether token homes
+    let new_location = increment_location(x, y, distance, 1);
+    return new_location == (5,0) || new_location == (5,2);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{TimeControl, DEFAULT_INACTIVITY_WINDOW};
+
+    fn game() -> Game {
+        Game {
+            player_1: "HcPlayerOne".into(),
+            player_2: "HcPlayerTwo".into(),
+            created_at: 0,
+            first_player: None,
+            time_control: TimeControl::Unlimited,
+            inactivity_window: DEFAULT_INACTIVITY_WINDOW,
+        }
+    }
+
+    #[test]
+    fn decodes_base58() {
+        assert_eq!(from_base58("2NEpo7TZRRrLZSi2U"), Some(b"Hello World!".to_vec()));
+        assert_eq!(from_base58("1112"), Some(vec![0, 0, 0, 1]));
+        assert_eq!(from_base58(""), Some(vec![]));
+        assert_eq!(from_base58("0OIl"), None);
+    }
+
+    #[test]
+    fn decodes_an_address_to_its_multihash() {
+        let bytes = from_base58("QmYwAPJzv5CZsnA625s3Xf2nemtYgPpHdWEz79ojWnPbdG").unwrap();
+        // sha2-256 and a 32 byte digest
+        assert_eq!(&bytes[..2], &[0x12, 0x20]);
+        assert_eq!(bytes.len(), 34);
+        assert_eq!(bytes[33], 0xdf);
+    }
+
+    #[test]
+    fn coin_flip_uses_the_lowest_bit_of_the_digest() {
+        let game = game();
+        for secret in &["a", "b", "c", "d", "e", "f", "g", "h"] {
+            let digest = from_base58(&coin_commitment(&format!("{}{}", secret, "other")).to_string()).unwrap();
+            let expected = if digest[digest.len() - 1] & 1 == 0 { &game.player_1 } else { &game.player_2 };
+            assert_eq!(coin_flip_winner(&game, secret, "other"), *expected);
+        }
+    }
+
+    #[test]
+    fn coin_flip_is_decided_by_both_secrets() {
+        let game = game();
+        let winners: Vec<Address> = (0..32)
+            .map(|n| coin_flip_winner(&game, &format!("secret {}", n), "fixed"))
+            .collect();
+        assert!(winners.contains(&game.player_1));
+        assert!(winners.contains(&game.player_2));
+        // the same secrets always give the same winner
+        assert_eq!(coin_flip_winner(&game, "secret 0", "fixed"), winners[0]);
+    }
+}
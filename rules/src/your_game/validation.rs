@@ -0,0 +1,493 @@
+
+use crate::game::Game;
+use crate::game_move::Move;
+use super::{
+    GameState,
+};
+use holochain_persistence_api::cas::content::Address;
+use super::state::Token;
+use super::moves::MoveType;
+use super::state::{increment_location, coin_commitment};
+
+/**
+ *
+ * To implement your own custom rule validation all you need to do is re-implement the function `is_valid` on `Move`
+ *
+ * This function  takes the current game and the game state (which includes all the existing moves)
+ * and determines if a new candidate move is valid. Typically this will involve first matching on the move type
+ * and then determining if the move is valid.
+ *
+ * It function must return Ok(()) if a move is valid and Err("Some error string") for an invalid move.
+ * It is useful to provide descriptive error strings as these can be visible to the end user.
+ *
+ */
+
+
+/// Timestamps are supplied by the players so allow this much clock drift between them
+pub const TIMESTAMP_TOLERANCE: u32 = 60;
+
+impl Move {
+    pub fn is_valid(&self, game: Game, game_state: GameState) -> Result<(), String> {
+        // Check if a move is valid given the current game and its state
+
+        // only the players of this game can make moves in it
+        is_player(self.author.clone(), &game)?;
+
+        // the game is not over
+        is_not_over(&game_state)?;
+
+        // the move is not timestamped before the previous move
+        is_after_previous_move(self.timestamp, &game, &game_state)?;
+
+        match self.move_type {
+            MoveType::CommitCoin{..} => {
+                // the game leaves the first player to chance and this player hasn't committed yet
+                can_commit_coin(self.author.clone(), &game, &game_state)
+            },
+            MoveType::RevealCoin{ref secret} => {
+                // both players have committed and the secret matches this player's commitment
+                can_reveal_coin(self.author.clone(), secret, &game, &game_state)
+            },
+            MoveType::ClaimTimeout => {
+                // it is the opponent's turn and they have run out of time
+                opponent_has_timed_out(self.author.clone(), self.timestamp, &game, &game_state)
+            },
+            MoveType::ClaimAbandoned => {
+                // it is the opponent's turn and they have not moved within the inactivity window
+                opponent_has_abandoned(self.author.clone(), self.timestamp, &game, &game_state)
+            },
+            MoveType::RequestTakeback => {
+                // the player has a move to take back and hasn't already asked
+                can_request_takeback(self.author.clone(), &game_state)
+            },
+            MoveType::AcceptTakeback => {
+                // only the opponent of the requester can accept, before any further move
+                can_accept_takeback(self.author.clone(), &game_state)
+            },
+            MoveType::OfferDraw => {
+                // only one draw offer can be open at a time, and a turned down offer
+                // can't be repeated until the player has moved a token
+                can_offer_draw(self.author.clone(), &game_state)
+            },
+            MoveType::AcceptDraw => {
+                // only the opponent of the player who offered can accept, before any further move
+                can_accept_draw(self.author.clone(), &game_state)
+            },
+            MoveType::Abort => {
+                // not both players have moved yet
+                can_abort(&game, &game_state)
+            },
+            MoveType::Resign => {
+                // a player can always give up
+                Ok(())
+            },
+            _ => {
+                self.is_valid_token_move(&game, &game_state)
+            },
+        }
+    }
+
+    fn is_valid_token_move(&self, game: &Game, game_state: &GameState) -> Result<(), String> {
+        // it is the player's turn (calculate from who was the last player to move and whether they
+        // landed on a rosette)
+        is_players_turn(self.author.clone(), &game, &game_state)?;
+
+        // get move length
+        let move_distance = match self.move_type {
+            MoveType::CreateToken{distance} => distance,
+            MoveType::MoveToken{x: _, y: _, distance} => distance,
+            _ => return Err("This move does not move a token".into()),
+        };
+
+        // the move length is no more than 4 tiles
+        is_valid_distance(move_distance)?;
+
+        let p = which_player(self.author.clone(), &game);
+
+        let destination = match self.move_type {
+            MoveType::CreateToken{distance} => {
+                // start one tile before the end of the board and increment, as evolve does
+                if p == 1 {
+                    increment_location(4, 0, distance, p)
+                } else {
+                    increment_location(4, 2, distance, p)
+                }
+            },
+            MoveType::MoveToken{x, y, distance} => {
+                increment_location(x, y, distance, p)
+            },
+            _ => return Err("This move does not move a token".into()),
+        };
+
+        // the move destination is not on top of another of the player's tokens (calculate by
+        // reducing game state)
+        player_can_move_to_tile(self.author.clone(), &game, &game_state, destination)?;
+
+        match self.move_type {
+            MoveType::CreateToken{distance: _} => {
+                // the player is not out of tokens (tokens on board + tokens home < 7)
+                player_has_token(self.author.clone(), &game, &game_state)?;
+            },
+            MoveType::MoveToken{x, y, distance} => {
+                // a token belonging to the player exists at the from coordinates of the move
+                token_exists(self.author.clone(), &game, &game_state, (x, y))?;
+
+                // the move is no more than one tile off the end of the board
+                isnt_overmoving((x, y), distance)?;
+            },
+            _ => return Err("This move does not move a token".into()),
+        }
+
+        Ok(())
+    }
+}
+
+fn is_player(player: Address, game: &Game) -> Result<(), String> {
+    if player == game.player_1 || player == game.player_2 {
+        Ok(())
+    } else {
+        Err("You are not playing in this game!".into())
+    }
+}
+
+fn is_not_over(game_state: &GameState) -> Result<(), String> {
+    if game_state.is_finished() {
+        Err("The game is already over!".into())
+    } else {
+        Ok(())
+    }
+}
+
+/// Players timestamp their own moves so the timestamp must be close to when the move was committed.
+/// Moves copied onto another player's chain while syncing are only checked for not being in the future.
+pub fn is_near_commit_time(timestamp: u32, committed_at: u32, committed_by_author: bool) -> Result<(), String> {
+    if timestamp > committed_at.saturating_add(TIMESTAMP_TOLERANCE) {
+        Err("A move can't be timestamped in the future!".into())
+    } else if committed_by_author && timestamp.saturating_add(TIMESTAMP_TOLERANCE) < committed_at {
+        Err("A move must be timestamped with the time it was made!".into())
+    } else {
+        Ok(())
+    }
+}
+
+fn is_after_previous_move(timestamp: u32, game: &Game, game_state: &GameState) -> Result<(), String> {
+    let previous = game_state.moves.last()
+        .map(|last_move| last_move.timestamp)
+        .unwrap_or(game.created_at);
+    if timestamp.saturating_add(TIMESTAMP_TOLERANCE) < previous {
+        Err("A move can't be timestamped before the previous move!".into())
+    } else {
+        Ok(())
+    }
+}
+
+fn opponent_has_timed_out(player: Address, timestamp: u32, game: &Game, game_state: &GameState) -> Result<(), String> {
+    let opponent = opponent_to_move(player, game, game_state)?;
+    match game_state.clock(game, &opponent) {
+        Some(remaining) => {
+            let elapsed = timestamp.saturating_sub(game_state.turn_started_at(game));
+            if elapsed > remaining {
+                Ok(())
+            } else {
+                Err("Your opponent still has time left!".into())
+            }
+        },
+        None => Err("This game is not played with a clock!".into()),
+    }
+}
+
+fn opponent_has_abandoned(player: Address, timestamp: u32, game: &Game, game_state: &GameState) -> Result<(), String> {
+    // an opponent who won't finish the coin flip is holding up the game as much as one who won't move
+    let opponent = if player == game.player_1 { &game.player_2 } else { &game.player_1 };
+    if waiting_on(game, game_state).as_ref() != Some(opponent) {
+        return Err("You can only make a claim while the game is waiting on your opponent!".into())
+    }
+    let idle = timestamp.saturating_sub(game_state.last_activity(game));
+    if idle > game.inactivity_window {
+        Ok(())
+    } else {
+        Err("Your opponent has not been inactive for long enough!".into())
+    }
+}
+
+// a claim can only be made against an opponent who is holding up the game
+fn opponent_to_move(player: Address, game: &Game, game_state: &GameState) -> Result<Address, String> {
+    let opponent = if player == game.player_1 { game.player_2.clone() } else { game.player_1.clone() };
+    if whose_turn(game, game_state) == Some(opponent.clone()) {
+        Ok(opponent)
+    } else {
+        Err("You can only make a claim while your opponent is to move!".into())
+    }
+}
+
+fn can_request_takeback(player: Address, game_state: &GameState) -> Result<(), String> {
+    if game_state.pending_takeback.is_some() {
+        return Err("A takeback has already been requested!".into())
+    }
+    if game_state.takeback_indices(&player).is_empty() {
+        Err("You have no move to take back!".into())
+    } else {
+        Ok(())
+    }
+}
+
+fn can_accept_takeback(player: Address, game_state: &GameState) -> Result<(), String> {
+    match &game_state.pending_takeback {
+        Some(requester) if *requester != player => Ok(()),
+        Some(_) => Err("You can't accept your own takeback request!".into()),
+        None => Err("There is no takeback to accept!".into()),
+    }
+}
+
+fn can_commit_coin(player: Address, game: &Game, game_state: &GameState) -> Result<(), String> {
+    if game.first_player.is_some() {
+        return Err("This game already says who moves first!".into())
+    }
+    if game_state.coin_commitment(&player).is_some() {
+        Err("You have already committed to the coin flip!".into())
+    } else {
+        Ok(())
+    }
+}
+
+fn can_reveal_coin(player: Address, secret: &str, game: &Game, game_state: &GameState) -> Result<(), String> {
+    if game_state.coin_commitment(&game.player_1).is_none() || game_state.coin_commitment(&game.player_2).is_none() {
+        return Err("Both players must commit before revealing!".into())
+    }
+    if game_state.coin_secret(&player).is_some() {
+        return Err("You have already revealed your secret!".into())
+    }
+    if game_state.coin_commitment(&player) == Some(coin_commitment(secret)) {
+        Ok(())
+    } else {
+        Err("The secret does not match your commitment!".into())
+    }
+}
+
+fn can_offer_draw(player: Address, game_state: &GameState) -> Result<(), String> {
+    if game_state.draw_offer.is_some() {
+        return Err("A draw has already been offered!".into())
+    }
+    let last_offer = game_state.moves.iter()
+        .rposition(|game_move| game_move.author == player && game_move.move_type == MoveType::OfferDraw);
+    if let Some(index) = last_offer {
+        let moved_since = game_state.moves[index + 1..].iter()
+            .any(|game_move| game_move.author == player && game_move.move_type.is_token_move());
+        if !moved_since {
+            return Err("You must move a token before offering a draw again!".into())
+        }
+    }
+    Ok(())
+}
+
+fn can_accept_draw(player: Address, game_state: &GameState) -> Result<(), String> {
+    match &game_state.draw_offer {
+        Some(offered_by) if *offered_by != player => Ok(()),
+        Some(_) => Err("You can't accept your own draw offer!".into()),
+        None => Err("There is no draw offer to accept!".into()),
+    }
+}
+
+fn can_abort(game: &Game, game_state: &GameState) -> Result<(), String> {
+    let token_moves = game_state.token_moves();
+    let p1_moved = token_moves.iter().any(|game_move| game_move.author == game.player_1);
+    let p2_moved = token_moves.iter().any(|game_move| game_move.author == game.player_2);
+    if p1_moved && p2_moved {
+        Err("Both players have moved, the game can no longer be aborted!".into())
+    } else {
+        Ok(())
+    }
+}
+
+fn is_players_turn(player: Address, game: &Game, game_state: &GameState) -> Result<(), String> {
+    match game_state.token_moves().last() {
+        Some(last_move) => {
+            if landed_on_rosette(last_move, &game) {
+                if last_move.author == player {
+                    // our player landed on rosette and plays again
+                    Ok(())
+                } else {
+                    // player's opponend landed on rosette and plays again
+                    Err("It is not your turn! ROSETTE".into())
+                }
+            } else {
+                // no rosette
+                if last_move.author == player {
+                    // our player just moved, opponent's turn
+                    Err("It is not your turn! NON-ROSETTE".into())
+                } else {
+                    // opponent just moved, our player's turn
+                    Ok(())
+                }
+            }
+
+        },
+        None => {
+            // the game names who starts or the players flip a coin for it
+            match game_state.first_player(game) {
+                Some(ref first_player) if *first_player == player => Ok(()),
+                Some(_) => Err("It is not your turn! The other player moves first".into()),
+                None => Err("Nobody can move until the coin flip decides who goes first!".into()),
+            }
+        }
+    }
+}
+
+/// Returns the player who is to move next, or None if the game is over or
+/// nobody has moved yet and the coin flip is undecided.
+pub fn whose_turn(game: &Game, game_state: &GameState) -> Option<Address> {
+    if game_state.is_finished() {
+        return None
+    }
+    match game_state.token_moves().last() {
+        Some(last_move) => {
+            if landed_on_rosette(last_move, game) {
+                Some(last_move.author.clone())
+            } else if last_move.author == game.player_1 {
+                Some(game.player_2.clone())
+            } else {
+                Some(game.player_1.clone())
+            }
+        },
+        None => game_state.first_player(game),
+    }
+}
+
+/// The player the game is waiting on, either to move or to finish the coin flip
+pub fn waiting_on(game: &Game, game_state: &GameState) -> Option<Address> {
+    whose_turn(game, game_state).or_else(|| game_state.coin_flip_waiting_on(game))
+}
+
+// figure out whether the token moved by a move landed on a rosette
+fn landed_on_rosette(last_move: &Move, game: &Game) -> bool {
+    let p = which_player(last_move.author.clone(), &game);
+    // there are two ways to do this because of the two move types
+    let location = match last_move.move_type {
+        MoveType::MoveToken{x, y, distance} => {
+            // player doesn't matter when calculating whether token is on rosette
+            increment_location(x, y, distance, 1)
+        },
+        MoveType::CreateToken{distance} => {
+            if p == 1 {
+                increment_location(4, 0, distance, 1)
+            } else { // p == 2
+                increment_location(4, 2, distance, 1)
+            }
+        },
+        _ => return false,
+    };
+    is_rosette(location.0, location.1)
+}
+
+fn player_can_move_to_tile(player: Address, game: &Game, game_state: &GameState, destination: (usize, usize)) -> Result<(), String> {
+    let x = destination.0;
+    let y = destination.1;
+    if player == game.player_1 {
+        // if player 1 is sending a token to a place where they already have a token
+        if game_state.p1_tokens.contains(&Token{x, y}) {
+            Err("You can't move a token onto another of your tokens! P1".into())
+        } else {
+            Ok(())
+        }
+    } else {
+        if game_state.p2_tokens.contains(&Token{x, y}) {
+            Err("You can't move a token onto another of your tokens! P2".into())
+        } else {
+            Ok(())
+        }
+    }
+}
+
+fn player_has_token(player: Address, game: &Game, game_state: &GameState) -> Result<(), String> {
+    if player == game.player_1 {
+        if game_state.p1_tokens.len() + game_state.p1_home < 7 {
+            Ok(())
+        } else {
+            Err("You are out of tokens! P1".into())
+        }
+    } else {
+        if game_state.p2_tokens.len() + game_state.p2_home < 7 {
+            Ok(())
+        } else {
+            Err("You are out of tokens! P2".into())
+        }
+    }
+}
+
+fn token_exists(player: Address, game: &Game, game_state: &GameState, origin: (usize, usize)) -> Result<(), String> {
+    let x = origin.0;
+    let y = origin.1;
+    if player == game.player_1 {
+        if game_state.p1_tokens.contains(&Token{x,y}) {
+            Ok(())
+        } else {
+            Err("There is not one of your tokens to move on the selected tile P1".into())
+        }
+    } else {
+        if game_state.p2_tokens.contains(&Token{x,y}) {
+            Ok(())
+        } else {
+            Err("There is not one of your tokens to move on the selected tile P2".into())
+        }
+    }
+}
+
+fn isnt_overmoving(origin: (usize, usize), distance: usize) -> Result<(), String> {
+    // split up by tiles of origin of the move
+    match origin {
+        (7,1) => {
+            // distance can't be 4
+            if distance > 3 {
+                Err("You must move off the board exactly! V3".into())
+            } else {
+                Ok(())
+            }
+        },
+        (7,0) | (7,2) => {
+            // distance can't be 3 or 4
+            if distance > 2 {
+                Err("You must move off the board exactly! V2".into())
+            } else {
+                Ok(())
+            }
+        },
+        (6,0) | (6,2) => {
+            // distance can't be 2, 3 or 4 (must be 1)
+            if distance > 1 {
+                Err("You must move off the board exactly! V1".into())
+            } else {
+                Ok(())
+            }
+        },
+        (_,_) => {
+            // from no other tiles can a 4-tile move end up more than one tile off the board
+            Ok(())
+        },
+    }
+}
+
+pub fn is_rosette(x: usize, y: usize) -> bool {
+    match (x,y) {
+        (0,0) | (0,2) | (3,1) | (6,0) | (6,2) => return true,
+        (_,_) => return false,
+    }
+}
+
+fn is_valid_distance(d: usize) -> Result<(), String> {
+    if d > 4 {
+        Err("You can't move more than 4 tiles!".into())
+    } else {
+        Ok(())
+    }
+}
+
+// find which player is moving (1 or 2)
+fn which_player(author: Address, game: &Game) -> usize {
+    if author == game.player_1 {
+        return 1;
+    } else if author == game.player_2 {
+        return 2;
+    } else {
+        return 100000000; // this should never happen
+    }
+}
@@ -22,6 +22,9 @@ const diorama = new Diorama({
   middleware: backwardCompatibilityMiddleware,
 })
 
+// moves are checked against the time they were committed so tests use the real clock
+const now = () => Math.floor(Date.now() / 1000)
+const sleep = ms => new Promise(resolve => setTimeout(resolve, ms))
 
 // Your tests here
 
@@ -40,7 +43,7 @@ diorama.registerScenario("Can create a new game", async (s, t, {alice, bob}) =>
     new_move: {
       game: create_game_result.Ok,
       move_type: { CreateToken: { distance: 4 } },
-      timestamp: 1
+      timestamp: now()
     }
   })
   console.log(move_1_result)
@@ -51,7 +54,7 @@ diorama.registerScenario("Can create a new game", async (s, t, {alice, bob}) =>
     new_move: {
       game: create_game_result.Ok,
       move_type: { CreateToken: { distance: 3 } },
-      timestamp: 2
+      timestamp: now()
     }
   })
   console.log(move_2_result)
@@ -62,7 +65,7 @@ diorama.registerScenario("Can create a new game", async (s, t, {alice, bob}) =>
     new_move: {
       game: create_game_result.Ok,
       move_type: { CreateToken: { distance: 3 } },
-      timestamp: 3
+      timestamp: now()
     }
   })
   console.log(move_3_result)
@@ -73,13 +76,732 @@ diorama.registerScenario("Can create a new game", async (s, t, {alice, bob}) =>
     new_move: {
       game: create_game_result.Ok,
       move_type: { CreateToken: { distance: 2 } },
-      timestamp: 4
+      timestamp: now()
     }
   })
   console.log(move_4_result)
   t.equal(move_4_result.Err, undefined)
 
+  // 6 - Both players can find the game again and see it is Bob's turn
+  const alice_games_result = await alice.call('main', 'get_my_games', {})
+  console.log(alice_games_result)
+  t.equal(alice_games_result.Ok.length, 1)
+  t.equal(alice_games_result.Ok[0].address, create_game_result.Ok)
+  t.equal(alice_games_result.Ok[0].opponent, bob.agentId)
+  t.equal(alice_games_result.Ok[0].whose_turn, bob.agentId)
+
+  const bob_games_result = await bob.call('main', 'get_my_games', {})
+  t.equal(bob_games_result.Ok[0].opponent, alice.agentId)
+
+  // 7 - Earlier states of the game can be looked up
+  const state_at_result = await alice.call('main', 'get_state_at', {
+    game_address: create_game_result.Ok,
+    index: 2
+  })
+  t.equal(state_at_result.Ok.moves.length, 2)
+  t.equal(state_at_result.Ok.p2_tokens.length, 2)
+  t.equal(state_at_result.Ok.p1_tokens.length, 0)
+
+  // 8 - Alice was told about Bob's moves directly
+  const notifications_result = await alice.call('main', 'get_notifications', {})
+  t.equal(notifications_result.Ok.length, 2)
+  t.equal(notifications_result.Ok[0].author, bob.agentId)
+  t.deepEqual(notifications_result.Ok[0].game_move.move_type, { CreateToken: { distance: 4 } })
+
+  // 9 - Replaying the game finds nothing wrong
+  const audit_result = await alice.call('main', 'audit_game', { game_address: create_game_result.Ok })
+  t.equal(audit_result.Ok.moves.length, 3)
+  t.equal(audit_result.Ok.first_invalid_move, null)
+  t.deepEqual(audit_result.Ok.forks, [])
+
+  // 10 - An exported copy of the game checks out, a tampered one doesn't
+  const bundle_result = await alice.call('main', 'export_game_bundle', { game_address: create_game_result.Ok })
+  t.equal(bundle_result.Ok.moves.length, 3)
+  const verify_result = await bob.call('main', 'verify_game_bundle', { bundle: bundle_result.Ok })
+  t.deepEqual(verify_result.Ok.problems, [])
+
+  bundle_result.Ok.moves[2].entry.timestamp = 100
+  const tampered_result = await bob.call('main', 'verify_game_bundle', { bundle: bundle_result.Ok })
+  t.notDeepEqual(tampered_result.Ok.problems, [])
+
+  // 11 - Bob catches up on Alice's last move, after which there is nothing left to sync
+  const sync_result = await bob.callSync('main', 'sync_game', { game_address: create_game_result.Ok })
+  t.equal(sync_result.Ok.moves_committed.length, 1)
+  const second_sync_result = await bob.callSync('main', 'sync_game', { game_address: create_game_result.Ok })
+  t.deepEqual(second_sync_result.Ok.moves_committed, [])
+
+  // 12 - Clients can fetch only the moves they haven't seen
+  const all_moves_result = await alice.call('main', 'get_moves_since', {
+    game_address: create_game_result.Ok
+  })
+  t.deepEqual(all_moves_result.Ok.map(m => m.index), [0, 1, 2])
+
+  const new_moves_result = await alice.call('main', 'get_moves_since', {
+    game_address: create_game_result.Ok,
+    last_known_move_address: all_moves_result.Ok[0].address
+  })
+  t.deepEqual(new_moves_result.Ok.map(m => m.index), [1, 2])
+  t.equal(new_moves_result.Ok[1].entry.author, alice.agentId)
+
+  const latest_moves_result = await bob.call('main', 'get_moves_since', {
+    game_address: create_game_result.Ok,
+    last_known_move_address: all_moves_result.Ok[1].address
+  })
+  t.deepEqual(latest_moves_result.Ok.map(m => m.address), [all_moves_result.Ok[2].address])
+  const no_moves_result = await bob.call('main', 'get_moves_since', {
+    game_address: create_game_result.Ok,
+    last_known_move_address: all_moves_result.Ok[2].address
+  })
+  t.deepEqual(no_moves_result.Ok, [])
+
+})
+
+diorama.registerScenario("Can challenge an agent who then accepts", async (s, t, {alice, bob}) => {
+
+  // 1 - Alice challenges Bob
+  const challenge_result = await alice.callSync('main', 'create_challenge', {
+    opponent: bob.agentId,
+    message: "fancy a game?",
+    timestamp: 0
+  })
+  console.log(challenge_result)
+  t.equal(challenge_result.Ok.length, 46)
+
+  // 2 - Bob sees the challenge
+  const incoming_result = await bob.call('main', 'get_incoming_challenges', {})
+  console.log(incoming_result)
+  t.equal(incoming_result.Ok.length, 1)
+
+  // 3 - Alice can't accept her own challenge
+  const bad_accept_result = await alice.callSync('main', 'accept_challenge', {
+    challenge_addr: challenge_result.Ok,
+    created_at: 1
+  })
+  t.equal(bad_accept_result.Ok, undefined)
+
+  // 4 - Bob accepts and a game now exists
+  const accept_result = await bob.callSync('main', 'accept_challenge', {
+    challenge_addr: challenge_result.Ok,
+    created_at: 1
+  })
+  console.log(accept_result)
+  t.equal(accept_result.Ok.length, 46)
+
+  const check_result = await alice.call('main', 'check_challenge', {
+    challenge_addr: challenge_result.Ok
+  })
+  t.equal(check_result.Ok[0].address, accept_result.Ok)
+
+  // 5 - Like any game that doesn't say otherwise, the agent who didn't create it moves first
+  const games_result = await bob.call('main', 'get_my_games', {})
+  t.equal(games_result.Ok[0].whose_turn, alice.agentId)
+})
+
+diorama.registerScenario("Two queued agents are matched into the same game", async (s, t, {alice, bob}) => {
+
+  await alice.callSync('main', 'join_queue', { timestamp: now() })
+  await bob.callSync('main', 'join_queue', { timestamp: now() })
+
+  // 1 - Bob finds Alice and creates the game
+  const bob_match_result = await bob.callSync('main', 'find_match', { timestamp: now() })
+  console.log(bob_match_result)
+  t.equal(bob_match_result.Ok.length, 46)
+
+  // 2 - Alice ends up in the same game
+  const alice_match_result = await alice.callSync('main', 'find_match', { timestamp: now() })
+  console.log(alice_match_result)
+  t.equal(alice_match_result.Ok, bob_match_result.Ok)
+})
+
+diorama.registerScenario("A queue entry must be timestamped with when it was made", async (s, t, {alice, bob}) => {
+
+  // 1 - Backdating an entry would jump the queue
+  const old_entry_result = await alice.callSync('main', 'join_queue', { timestamp: now() - 60 * 60 })
+  console.log(old_entry_result)
+  t.notEqual(old_entry_result.Err, undefined)
+
+  // 2 - With only one agent queued at the right time nobody is matched
+  await alice.callSync('main', 'join_queue', { timestamp: now() })
+  const match_result = await alice.callSync('main', 'find_match', { timestamp: now() })
+  t.equal(match_result.Ok, null)
+})
+
+diorama.registerScenario("Proposals and responses are returned a page at a time", async (s, t, {alice, bob}) => {
+
+  const proposals = []
+  for (const timestamp of [0, 1, 2, 2 * 60 * 60]) {
+    proposals.push((await alice.callSync('main', 'create_proposal', { message: 'game ' + timestamp, timestamp })).Ok)
+  }
+
+  // 1 - A full page stops early and hands back a cursor for the rest
+  const first_page = await bob.call('main', 'get_proposals', { timestamp: 2 * 60 * 60, limit: 2 })
+  t.equal(first_page.Ok.items.length, 2)
+  t.equal(first_page.Ok.items[0].address, proposals[3])
+  t.notEqual(first_page.Ok.next_cursor, null)
+
+  const second_page = await bob.call('main', 'get_proposals', { timestamp: 2 * 60 * 60, limit: 2, cursor: first_page.Ok.next_cursor })
+  t.equal(second_page.Ok.items.length, 2)
+  t.equal(second_page.Ok.next_cursor, null)
+  const seen = first_page.Ok.items.concat(second_page.Ok.items).map(p => p.address).sort()
+  t.deepEqual(seen, proposals.slice().sort())
+
+  // 2 - A removed proposal is skipped instead of failing the whole page
+  await alice.callSync('main', 'remove_proposal', { proposal_addr: proposals[0] })
+  const after_remove = await bob.call('main', 'get_proposals', { timestamp: 2 * 60 * 60 })
+  t.equal(after_remove.Ok.items.length, 3)
+
+  // 3 - Responses to a proposal page the same way
+  for (const created_at of [1, 2, 3]) {
+    await bob.callSync('main', 'accept_proposal', { proposal_addr: proposals[1], created_at })
+  }
+  const first_responses = await alice.call('main', 'check_responses', { proposal_addr: proposals[1], limit: 2 })
+  t.equal(first_responses.Ok.items.length, 2)
+  t.equal(first_responses.Ok.next_cursor, '2')
+  const second_responses = await alice.call('main', 'check_responses', { proposal_addr: proposals[1], limit: 2, cursor: '2' })
+  t.equal(second_responses.Ok.items.length, 1)
+  t.equal(second_responses.Ok.next_cursor, null)
+})
+
+diorama.registerScenario("Finished games are recorded and rated", async (s, t, {alice, bob}) => {
+
+  const game = (await alice.callSync('main', 'create_game', { opponent: bob.agentId, timestamp: now(), first_move: 'Me' })).Ok
+  await alice.callSync('main', 'make_move', {
+    new_move: { game, move_type: { CreateToken: { distance: 2 } }, timestamp: now() }
+  })
+  const resigned_at = now()
+  await bob.callSync('main', 'make_move', {
+    new_move: { game, move_type: 'Resign', timestamp: resigned_at }
+  })
+
+  // 1 - Bob's resignation was recorded and both ratings moved
+  const alice_rating = await alice.call('main', 'get_rating', { agent: alice.agentId })
+  t.equal(Math.round(alice_rating.Ok.rating), 1516)
+  t.equal(alice_rating.Ok.history.length, 1)
+  t.equal(alice_rating.Ok.history[0].score, 1)
+  const bob_rating = await alice.call('main', 'get_rating', { agent: bob.agentId })
+  t.equal(Math.round(bob_rating.Ok.rating), 1484)
+
+  const result = {
+    game,
+    player_1: alice.agentId,
+    player_2: bob.agentId,
+    outcome: { Resigned: { winner: alice.agentId } },
+    finished_at: resigned_at
+  }
+
+  // 2 - Bob can't record a result the moves don't support
+  const forged_result = await bob.callSync('main', 'record_game_result', {
+    result: Object.assign({}, result, { outcome: { Resigned: { winner: bob.agentId } } })
+  })
+  t.equal(forged_result.Ok, undefined)
+
+  // 3 - Alice countersigns the real result, which is only counted once
+  await alice.callSync('main', 'sync_game', { game_address: game })
+  const countersign_result = await alice.callSync('main', 'record_game_result', { result })
+  t.equal(countersign_result.Err, undefined)
+  const rating_after = await bob.call('main', 'get_rating', { agent: alice.agentId })
+  t.equal(rating_after.Ok.history.length, 1)
+})
+
+diorama.registerScenario("The leaderboard only ranks games won by bringing every token home", async (s, t, {alice, bob}) => {
+
+  const play = async (timestamp, loser_move) => {
+    const game = (await alice.callSync('main', 'create_game', { opponent: bob.agentId, timestamp, first_move: 'Me' })).Ok
+    await alice.callSync('main', 'make_move', {
+      new_move: { game, move_type: { CreateToken: { distance: 2 } }, timestamp: now() }
+    })
+    await bob.callSync('main', 'make_move', { new_move: { game, move_type: loser_move, timestamp: now() } })
+    return game
+  }
+  const resigned = await play(1, 'Resign')
+  await play(2, 'Abort')
+
+  // 1 - The resignation is rated like any other result
+  const rating_result = await bob.call('main', 'get_rating', { agent: alice.agentId })
+  t.equal(rating_result.Ok.history[0].game, resigned)
+
+  // 2 - But neither game is a home run so nobody is ranked
+  const leaderboard_result = await bob.call('main', 'get_leaderboard', {})
+  t.deepEqual(leaderboard_result.Ok.items, [])
+  t.equal(leaderboard_result.Ok.next_cursor, null)
+})
+
+diorama.registerScenario("A tournament pairs its players and keeps standings", async (s, t, {alice, bob}) => {
+
+  // 1 - Tournaments are played with this DNA's rules
+  const wrong_variant_result = await alice.callSync('main', 'create_tournament', {
+    name: 'ladder', participants: [alice.agentId, bob.agentId], format: 'Swiss', rounds: 2, variant: 'Masters', timestamp: 0
+  })
+  t.equal(wrong_variant_result.Ok, undefined)
+
+  const tournament_addr = (await alice.callSync('main', 'create_tournament', {
+    name: 'ladder', participants: [alice.agentId, bob.agentId], format: 'Swiss', rounds: 2, variant: 'Finkel', timestamp: 0
+  })).Ok
+  await alice.callSync('main', 'register_for_tournament', { tournament_addr })
+  await bob.callSync('main', 'register_for_tournament', { tournament_addr })
+
+  // 2 - The pairings Bob works out are the ones the round is started with
+  const expected_pairings = await bob.call('main', 'get_pairings', { tournament_addr, round: 1 })
+  t.equal(expected_pairings.Ok.pairings.length, 1)
+  const round_result = await alice.callSync('main', 'start_round', { tournament_addr, round: 1 })
+  t.equal(round_result.Err, undefined)
+  const pairings = await bob.call('main', 'get_pairings', { tournament_addr, round: 1 })
+  t.deepEqual(pairings.Ok, expected_pairings.Ok)
+
+  // 3 - Bob resigns and once the result is counted Alice leads
+  const game = pairings.Ok.pairings[0].game
+  await bob.callSync('main', 'make_move', { new_move: { game, move_type: 'Resign', timestamp: now() } })
+  const record_result = await alice.callSync('main', 'record_tournament_result', { tournament_addr, game_addr: game })
+  t.equal(record_result.Err, undefined)
+  const standings = await bob.call('main', 'get_standings', { tournament_addr })
+  t.deepEqual(standings.Ok.map(standing => standing.agent), [alice.agentId, bob.agentId])
+  t.equal(standings.Ok[0].score, 1)
+
+  // 4 - With only two players a second Swiss round would be a rematch, so it is refused
+  const second_round_result = await alice.callSync('main', 'start_round', { tournament_addr, round: 2 })
+  t.equal(second_round_result.Ok, undefined)
+})
+
+diorama.registerScenario("Players can ask for a rematch and play a series", async (s, t, {alice, bob}) => {
+
+  const series_addr = (await alice.callSync('main', 'create_series', { opponent: bob.agentId, best_of: 3, timestamp: now() })).Ok
+  const first_game = (await bob.call('main', 'get_series_state', { series_addr })).Ok.games[0]
+
+  // 1 - A rematch can only be asked for once the game has finished
+  const early_rematch_result = await alice.callSync('main', 'rematch', { game_address: first_game })
+  t.equal(early_rematch_result.Ok, undefined)
+
+  // 2 - Both players asking at once still start a single rematch
+  await bob.callSync('main', 'make_move', { new_move: { game: first_game, move_type: 'Resign', timestamp: now() } })
+  const [alice_rematch, bob_rematch] = await Promise.all([
+    alice.callSync('main', 'rematch', { game_address: first_game }),
+    bob.callSync('main', 'rematch', { game_address: first_game }),
+  ])
+  t.equal(alice_rematch.Ok, bob_rematch.Ok)
+  const second_state = await alice.call('main', 'get_series_state', { series_addr })
+  t.deepEqual(second_state.Ok.games, [first_game, alice_rematch.Ok])
+  t.equal(second_state.Ok.p1_wins, 1)
+
+  // 3 - Bob moved first last time so Alice moves first in the rematch
+  const bob_first_result = await bob.callSync('main', 'make_move', {
+    new_move: { game: alice_rematch.Ok, move_type: { CreateToken: { distance: 2 } }, timestamp: now() }
+  })
+  t.equal(bob_first_result.Ok, undefined)
+
+  // 4 - The series ends as soon as a player has won two games
+  await alice.callSync('main', 'make_move', { new_move: { game: alice_rematch.Ok, move_type: 'Resign', timestamp: now() } })
+  const third_game = (await bob.callSync('main', 'rematch', { game_address: alice_rematch.Ok })).Ok
+  await bob.callSync('main', 'make_move', { new_move: { game: third_game, move_type: 'Resign', timestamp: now() } })
+  const final_state = await bob.call('main', 'get_series_state', { series_addr })
+  t.equal(final_state.Ok.games.length, 3)
+  t.equal(final_state.Ok.p1_wins, 2)
+  t.equal(final_state.Ok.p2_wins, 1)
+  t.equal(final_state.Ok.finished, true)
+  t.equal(final_state.Ok.winner, alice.agentId)
+
+  // 5 - A rematch after that is played outside the series
+  const extra_game = (await alice.callSync('main', 'rematch', { game_address: third_game })).Ok
+  t.notEqual(extra_game, undefined)
+  const after_state = await alice.call('main', 'get_series_state', { series_addr })
+  t.equal(after_state.Ok.games.length, 3)
 })
 
+diorama.registerScenario("Can claim a game after the opponent stops playing", async (s, t, {alice, bob}) => {
+
+  const create_game_result = await alice.callSync('main', 'create_game', {
+    opponent: bob.agentId,
+    timestamp: now(),
+    inactivity_window: 2,
+    first_move: 'Me'
+  })
+  t.equal(create_game_result.Ok.length, 46)
+
+  await alice.callSync('main', 'make_move', {
+    new_move: {
+      game: create_game_result.Ok,
+      move_type: { CreateToken: { distance: 2 } },
+      timestamp: now()
+    }
+  })
+
+  // 1 - Bob has not been gone long enough yet
+  const early_claim_result = await alice.callSync('main', 'make_move', {
+    new_move: {
+      game: create_game_result.Ok,
+      move_type: 'ClaimAbandoned',
+      timestamp: now()
+    }
+  })
+  t.equal(early_claim_result.Ok, undefined)
+
+  // 2 - Nor can Alice date her claim to after the window has passed
+  const forged_claim_result = await alice.callSync('main', 'make_move', {
+    new_move: {
+      game: create_game_result.Ok,
+      move_type: 'ClaimAbandoned',
+      timestamp: now() + 24 * 60 * 60
+    }
+  })
+  t.equal(forged_claim_result.Ok, undefined)
+
+  // 3 - Once the window has passed Alice can claim the win
+  await sleep(3000)
+  const state_result = await alice.call('main', 'get_state', {
+    game_address: create_game_result.Ok,
+    timestamp: now()
+  })
+  t.equal(state_result.Ok.inactive.can_claim, true)
+
+  const claim_result = await alice.callSync('main', 'make_move', {
+    new_move: {
+      game: create_game_result.Ok,
+      move_type: 'ClaimAbandoned',
+      timestamp: now()
+    }
+  })
+  t.equal(claim_result.Err, undefined)
+
+  const final_state_result = await alice.call('main', 'get_state', {
+    game_address: create_game_result.Ok
+  })
+  t.deepEqual(final_state_result.Ok.outcome, { Abandoned: { winner: alice.agentId } })
+})
+
+diorama.registerScenario("Can claim a game after the opponent runs out of time", async (s, t, {alice, bob}) => {
+
+  const game = (await alice.callSync('main', 'create_game', {
+    opponent: bob.agentId,
+    timestamp: now(),
+    time_control: { Fischer: { initial_seconds: 2, increment_seconds: 0 } },
+    first_move: 'Me'
+  })).Ok
+  await alice.callSync('main', 'make_move', {
+    new_move: { game, move_type: { CreateToken: { distance: 2 } }, timestamp: now() }
+  })
+
+  // 1 - Bob still has time on his clock
+  const early_claim_result = await alice.callSync('main', 'make_move', {
+    new_move: { game, move_type: 'ClaimTimeout', timestamp: now() }
+  })
+  t.equal(early_claim_result.Ok, undefined)
+
+  // 2 - Dating the claim to after Bob's clock runs out doesn't get around that
+  const premature_claim_result = await alice.callSync('main', 'make_move', {
+    new_move: { game, move_type: 'ClaimTimeout', timestamp: now() + 60 * 60 }
+  })
+  t.equal(premature_claim_result.Ok, undefined)
+
+  // 3 - Once his time is really up Alice wins
+  await sleep(3000)
+  const claim_result = await alice.callSync('main', 'make_move', {
+    new_move: { game, move_type: 'ClaimTimeout', timestamp: now() }
+  })
+  t.equal(claim_result.Err, undefined)
+  const state_result = await bob.call('main', 'get_state', { game_address: game })
+  t.deepEqual(state_result.Ok.outcome, { Timeout: { winner: alice.agentId } })
+})
+
+diorama.registerScenario("Can take back a move when the opponent agrees", async (s, t, {alice, bob}) => {
+
+  const create_game_result = await alice.callSync('main', 'create_game', {
+    opponent: bob.agentId,
+    timestamp: 0,
+    first_move: 'Me'
+  })
+  const game = create_game_result.Ok
+
+  await alice.callSync('main', 'make_move', {
+    new_move: { game, move_type: { CreateToken: { distance: 2 } }, timestamp: now() }
+  })
+  const request_result = await alice.callSync('main', 'make_move', {
+    new_move: { game, move_type: 'RequestTakeback', timestamp: now() }
+  })
+  t.equal(request_result.Err, undefined)
+
+  // 1 - Alice can't accept her own request
+  const self_accept_result = await alice.callSync('main', 'make_move', {
+    new_move: { game, move_type: 'AcceptTakeback', timestamp: now() }
+  })
+  t.equal(self_accept_result.Ok, undefined)
+
+  // 2 - Bob accepts and Alice's token leaves the board
+  const accept_result = await bob.callSync('main', 'make_move', {
+    new_move: { game, move_type: 'AcceptTakeback', timestamp: now() }
+  })
+  t.equal(accept_result.Err, undefined)
+
+  const state_result = await alice.call('main', 'get_state', { game_address: game })
+  t.deepEqual(state_result.Ok.p1_tokens, [])
+  t.deepEqual(state_result.Ok.taken_back, [0])
+})
+
+diorama.registerScenario("Players can agree a draw or abort a game", async (s, t, {alice, bob}) => {
+
+  const draw_game = (await alice.callSync('main', 'create_game', { opponent: bob.agentId, timestamp: 0 })).Ok
+
+  // 1 - A draw can only be accepted once offered, and not by the player who offered it
+  const early_accept_result = await bob.callSync('main', 'make_move', {
+    new_move: { game: draw_game, move_type: 'AcceptDraw', timestamp: now() }
+  })
+  t.equal(early_accept_result.Ok, undefined)
+
+  await alice.callSync('main', 'make_move', {
+    new_move: { game: draw_game, move_type: 'OfferDraw', timestamp: now() }
+  })
+  const own_accept_result = await alice.callSync('main', 'make_move', {
+    new_move: { game: draw_game, move_type: 'AcceptDraw', timestamp: now() }
+  })
+  t.equal(own_accept_result.Ok, undefined)
+
+  await bob.callSync('main', 'make_move', {
+    new_move: { game: draw_game, move_type: 'AcceptDraw', timestamp: now() }
+  })
+  const draw_state = await alice.call('main', 'get_state', { game_address: draw_game })
+  t.equal(draw_state.Ok.outcome, 'Draw')
+
+  // 2 - A turned down offer can't be repeated until the player has moved a token
+  const repeat_game = (await alice.callSync('main', 'create_game', { opponent: bob.agentId, timestamp: 5, first_move: 'Me' })).Ok
+  const repeat_move = (author, move_type) => author.callSync('main', 'make_move', {
+    new_move: { game: repeat_game, move_type, timestamp: now() }
+  })
+  await repeat_move(alice, { CreateToken: { distance: 1 } })
+  await repeat_move(alice, 'OfferDraw')
+  await repeat_move(bob, { CreateToken: { distance: 2 } })
+  const repeat_offer_result = await repeat_move(alice, 'OfferDraw')
+  t.equal(repeat_offer_result.Ok, undefined)
+  await repeat_move(alice, { CreateToken: { distance: 2 } })
+  const later_offer_result = await repeat_move(alice, 'OfferDraw')
+  t.equal(later_offer_result.Err, undefined)
+
+  // 3 - The finished game can be written out as notation and replayed
+  const notation_result = await alice.call('main', 'export_game', { game_address: draw_game })
+  t.ok(notation_result.Ok.includes('[Result "1/2-1/2"]'))
+  t.ok(notation_result.Ok.includes('1. P1 offer-draw @'))
+  const replay_result = await alice.call('main', 'replay_notation', { notation: notation_result.Ok })
+  t.equal(replay_result.Ok.outcome, 'Draw')
+
+  // 4 - A game can be aborted until both players have moved
+  const abort_game = (await alice.callSync('main', 'create_game', { opponent: bob.agentId, timestamp: 10, first_move: 'Me' })).Ok
+  await alice.callSync('main', 'make_move', {
+    new_move: { game: abort_game, move_type: { CreateToken: { distance: 2 } }, timestamp: now() }
+  })
+  const abort_result = await bob.callSync('main', 'make_move', {
+    new_move: { game: abort_game, move_type: 'Abort', timestamp: now() }
+  })
+  t.equal(abort_result.Err, undefined)
+  const abort_state = await alice.call('main', 'get_state', { game_address: abort_game })
+  t.equal(abort_state.Ok.outcome, 'Aborted')
+})
+
+diorama.registerScenario("Players flip a coin to decide who moves first", async (s, t, {alice, bob}) => {
+
+  const game = (await alice.callSync('main', 'create_game', {
+    opponent: bob.agentId,
+    timestamp: 0,
+    first_move: 'CoinFlip'
+  })).Ok
+
+  // 1 - Nobody can move before the coin is flipped
+  const early_move_result = await bob.callSync('main', 'make_move', {
+    new_move: { game, move_type: { CreateToken: { distance: 2 } }, timestamp: now() }
+  })
+  t.equal(early_move_result.Ok, undefined)
+
+  // Alice keeps her secret on her chain, saving another one later doesn't change her commitment
+  const alice_commitment = (await alice.callSync('main', 'save_coin_secret', { game_address: game, secret: 'alice secret' })).Ok
+  t.equal(alice_commitment, (await alice.call('main', 'get_coin_commitment', { secret: 'alice secret' })).Ok)
+  const resaved_commitment = (await alice.callSync('main', 'save_coin_secret', { game_address: game, secret: 'another secret' })).Ok
+  t.equal(resaved_commitment, alice_commitment)
+  const bob_commitment = (await bob.call('main', 'get_coin_commitment', { secret: 'bob secret' })).Ok
+  await alice.callSync('main', 'make_move', {
+    new_move: { game, move_type: { CommitCoin: { commitment: alice_commitment } }, timestamp: now() }
+  })
+  await bob.callSync('main', 'make_move', {
+    new_move: { game, move_type: { CommitCoin: { commitment: bob_commitment } }, timestamp: now() }
+  })
+
+  // 2 - A secret must match its commitment
+  const bad_reveal_result = await alice.callSync('main', 'make_move', {
+    new_move: { game, move_type: { RevealCoin: { secret: 'something else' } }, timestamp: now() }
+  })
+  t.equal(bad_reveal_result.Ok, undefined)
+
+  const alice_secret = (await alice.call('main', 'get_coin_secret', { game_address: game })).Ok
+  t.equal(alice_secret, 'alice secret')
+  await alice.callSync('main', 'make_move', {
+    new_move: { game, move_type: { RevealCoin: { secret: alice_secret } }, timestamp: now() }
+  })
+  await bob.callSync('main', 'make_move', {
+    new_move: { game, move_type: { RevealCoin: { secret: 'bob secret' } }, timestamp: now() }
+  })
+
+  // 3 - Only the winner of the coin flip can move first
+  const state_result = await alice.call('main', 'get_state', { game_address: game })
+  const first_player = state_result.Ok.first_player
+  t.ok(first_player === alice.agentId || first_player === bob.agentId)
+
+  const second = first_player === alice.agentId ? bob : alice
+  const wrong_player_result = await second.callSync('main', 'make_move', {
+    new_move: { game, move_type: { CreateToken: { distance: 2 } }, timestamp: now() }
+  })
+  t.equal(wrong_player_result.Ok, undefined)
+})
+
+diorama.registerScenario("Can claim a game when the opponent won't reveal their coin", async (s, t, {alice, bob}) => {
+
+  const game = (await alice.callSync('main', 'create_game', {
+    opponent: bob.agentId,
+    timestamp: now(),
+    inactivity_window: 2,
+    first_move: 'CoinFlip'
+  })).Ok
+  const alice_commitment = (await alice.call('main', 'get_coin_commitment', { secret: 'alice secret' })).Ok
+  const bob_commitment = (await bob.call('main', 'get_coin_commitment', { secret: 'bob secret' })).Ok
+  await alice.callSync('main', 'make_move', {
+    new_move: { game, move_type: { CommitCoin: { commitment: alice_commitment } }, timestamp: now() }
+  })
+  await bob.callSync('main', 'make_move', {
+    new_move: { game, move_type: { CommitCoin: { commitment: bob_commitment } }, timestamp: now() }
+  })
+  await alice.callSync('main', 'make_move', {
+    new_move: { game, move_type: { RevealCoin: { secret: 'alice secret' } }, timestamp: now() }
+  })
+
+  // 1 - Bob, who has yet to reveal, can't claim the game Alice is waiting on
+  await sleep(3000)
+  const bob_claim_result = await bob.callSync('main', 'make_move', {
+    new_move: { game, move_type: 'ClaimAbandoned', timestamp: now() }
+  })
+  t.equal(bob_claim_result.Ok, undefined)
+
+  // 2 - Bob never reveals so Alice can claim the game
+  const state_result = await alice.call('main', 'get_state', { game_address: game, timestamp: now() })
+  t.equal(state_result.Ok.inactive.player, bob.agentId)
+  t.equal(state_result.Ok.inactive.can_claim, true)
+  const claim_result = await alice.callSync('main', 'make_move', {
+    new_move: { game, move_type: 'ClaimAbandoned', timestamp: now() }
+  })
+  t.equal(claim_result.Err, undefined)
+  const final_state_result = await bob.call('main', 'get_state', { game_address: game })
+  t.deepEqual(final_state_result.Ok.outcome, { Abandoned: { winner: alice.agentId } })
+})
+
+diorama.registerScenario("Players can chat in a game", async (s, t, {alice, bob}) => {
+
+  const game = (await alice.callSync('main', 'create_game', { opponent: bob.agentId, timestamp: 0 })).Ok
+
+  await alice.callSync('main', 'send_game_message', { game_address: game, text: 'good luck!', timestamp: 1 })
+  await bob.callSync('main', 'send_game_message', { game_address: game, text: 'you too', timestamp: 2 })
+
+  // 1 - Empty messages are rejected
+  const empty_result = await bob.callSync('main', 'send_game_message', { game_address: game, text: '  ', timestamp: 3 })
+  t.equal(empty_result.Ok, undefined)
+
+  const messages_result = await alice.call('main', 'get_game_messages', { game_address: game })
+  t.deepEqual(messages_result.Ok.map(m => m.text), ['good luck!', 'you too'])
+  t.equal(messages_result.Ok[1].author, bob.agentId)
+})
+
+diorama.registerScenario("Players can choose unique nicknames", async (s, t, {alice, bob}) => {
+
+  // 1 - Alice sets her profile and appears in the directory
+  await alice.callSync('main', 'set_profile', { nickname: 'alice', bio: 'likes rosettes', preferred_variant: 'Finkel', timestamp: now() })
+  const profile_result = await bob.call('main', 'get_profile', { agent: alice.agentId })
+  t.equal(profile_result.Ok.nickname, 'alice')
+  t.equal(profile_result.Ok.agent, alice.agentId)
+
+  // 2 - Bob can't take the same nickname, whatever the case
+  const taken_result = await bob.callSync('main', 'set_profile', { nickname: 'Alice', bio: '', preferred_variant: null, timestamp: now() })
+  t.equal(taken_result.Ok, undefined)
+
+  // 3 - Once Alice renames herself her old nickname is free
+  await alice.callSync('main', 'set_profile', { nickname: 'alice2', bio: 'likes rosettes', preferred_variant: 'Finkel', timestamp: now() })
+  await bob.callSync('main', 'set_profile', { nickname: 'alice', bio: '', preferred_variant: null, timestamp: now() })
+
+  const directory_result = await alice.call('main', 'get_agent_directory', {})
+  t.deepEqual(directory_result.Ok.items.map(p => p.nickname), ['alice', 'alice2'])
+  t.equal(directory_result.Ok.items[0].agent, bob.agentId)
+
+  // 4 - Claiming the same nickname at once leaves it with the earlier claim
+  await Promise.all([
+    alice.callSync('main', 'set_profile', { nickname: 'rosette', bio: '', preferred_variant: null, timestamp: now() - 10 }),
+    bob.callSync('main', 'set_profile', { nickname: 'rosette', bio: '', preferred_variant: null, timestamp: now() }),
+  ])
+  const alice_profile_result = await bob.call('main', 'get_profile', { agent: alice.agentId })
+  t.equal(alice_profile_result.Ok.nickname, 'rosette')
+  const claims_result = await bob.call('main', 'get_agent_directory', {})
+  t.deepEqual(claims_result.Ok.items.filter(p => p.nickname === 'rosette').map(p => p.agent), [alice.agentId])
+
+  // 5 - A nickname can't be claimed ahead of time
+  const future_result = await alice.callSync('main', 'set_profile', { nickname: 'later', bio: '', preferred_variant: null, timestamp: now() + 3600 })
+  t.equal(future_result.Ok, undefined)
+})
+
+diorama.registerScenario("Blocked agents are hidden from matchmaking", async (s, t, {alice, bob}) => {
+
+  const proposal = (await bob.callSync('main', 'create_proposal', { message: 'anyone?', timestamp: 0 })).Ok
+  await alice.callSync('main', 'block_agent', { agent: bob.agentId, timestamp: 1 })
+
+  // 1 - Alice no longer sees Bob's proposals and can't start a game with him
+  const proposals_result = await alice.call('main', 'get_proposals', { timestamp: 2 })
+  t.deepEqual(proposals_result.Ok.items, [])
+  const create_game_result = await alice.callSync('main', 'create_game', { opponent: bob.agentId, timestamp: 2 })
+  t.equal(create_game_result.Ok, undefined)
+
+  // 2 - Bob can still see his own proposal
+  const bob_proposals_result = await bob.call('main', 'get_proposals', { timestamp: 2 })
+  t.equal(bob_proposals_result.Ok.items.length, 1)
+  t.equal(bob_proposals_result.Ok.items[0].address, proposal)
+
+  // 3 - Unblocking brings everything back
+  await alice.callSync('main', 'unblock_agent', { agent: bob.agentId, timestamp: 3 })
+  const blocked_result = await alice.call('main', 'get_blocked_agents', {})
+  t.deepEqual(blocked_result.Ok, [])
+  const unblocked_proposals_result = await alice.call('main', 'get_proposals', { timestamp: 4 })
+  t.equal(unblocked_proposals_result.Ok.items.length, 1)
+
+  // 4 - A page is filled with proposals Alice can see rather than cut short by blocked ones
+  await bob.callSync('main', 'create_proposal', { message: 'anyone else?', timestamp: 5 })
+  const alice_proposal = (await alice.callSync('main', 'create_proposal', { message: 'me!', timestamp: 6 })).Ok
+  await bob.callSync('main', 'accept_proposal', { proposal_addr: alice_proposal, created_at: 7 })
+  await alice.callSync('main', 'block_agent', { agent: bob.agentId, timestamp: 8 })
+  const page_result = await alice.call('main', 'get_proposals', { timestamp: 9, limit: 1 })
+  t.deepEqual(page_result.Ok.items.map(item => item.address), [alice_proposal])
+
+  // 5 - Games Bob started from Alice's proposal are hidden from her responses too
+  const responses_result = await alice.call('main', 'check_responses', { proposal_addr: alice_proposal })
+  t.deepEqual(responses_result.Ok.items, [])
+  t.equal(responses_result.Ok.next_cursor, null)
+})
+
+
+diorama.registerScenario("Blocked agents can't start a game any other way", async (s, t, {alice, bob}) => {
+
+  const challenge = (await bob.callSync('main', 'create_challenge', { opponent: alice.agentId, message: 'rematch?', timestamp: 0 })).Ok
+  const proposal = (await bob.callSync('main', 'create_proposal', { message: 'anyone?', timestamp: 0 })).Ok
+  await alice.callSync('main', 'block_agent', { agent: bob.agentId, timestamp: 1 })
+
+  // 1 - Bob's challenge is hidden from Alice and can't be accepted
+  const incoming_result = await alice.call('main', 'get_incoming_challenges', {})
+  t.deepEqual(incoming_result.Ok, [])
+  const accept_challenge_result = await alice.callSync('main', 'accept_challenge', { challenge_addr: challenge, created_at: 2 })
+  t.equal(accept_challenge_result.Ok, undefined)
+
+  // 2 - Alice can't challenge Bob or accept his proposal either
+  const challenge_result = await alice.callSync('main', 'create_challenge', { opponent: bob.agentId, message: 'oops', timestamp: 2 })
+  t.equal(challenge_result.Ok, undefined)
+  const accept_proposal_result = await alice.callSync('main', 'accept_proposal', { proposal_addr: proposal, created_at: 2 })
+  t.equal(accept_proposal_result.Ok, undefined)
+
+  // 3 - Being paired with Bob in the queue takes Alice out of it rather than into a game
+  await alice.callSync('main', 'join_queue', { timestamp: now() })
+  await bob.callSync('main', 'join_queue', { timestamp: now() })
+  const bob_match_result = await bob.callSync('main', 'find_match', { timestamp: now() })
+  t.equal(bob_match_result.Ok.length, 46)
+  const alice_match_result = await alice.callSync('main', 'find_match', { timestamp: now() })
+  t.equal(alice_match_result.Ok, undefined)
+  const retry_result = await alice.callSync('main', 'find_match', { timestamp: now() })
+  t.equal(retry_result.Ok, null)
+})
 
 diorama.run()
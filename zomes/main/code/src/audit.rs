@@ -0,0 +1,141 @@
+use hdk::{
+    error::ZomeApiResult,
+    holochain_persistence_api::{
+        cas::content::Address,
+    },
+    holochain_json_api::{
+        error::JsonError, json::JsonString,
+    },
+    holochain_core_types::{
+        link::LinkMatch,
+    }
+};
+
+use crate::game;
+use crate::game_move::Move;
+use crate::GameState;
+
+/// What was found when replaying a game from the DHT
+#[derive(Clone, Debug, Serialize, Deserialize, DefaultJson)]
+pub struct AuditReport {
+    pub game: Address,
+    pub moves: Vec<Address>,
+    pub first_invalid_move: Option<InvalidMove>,
+    // a game or move with more than one move linked after it
+    pub forks: Vec<Fork>,
+    // moves of this game on the caller's chain which can't be reached through the DHT links
+    pub local_only: Vec<Address>,
+    // moves reachable through the DHT links which are missing from the caller's chain
+    pub missing_locally: Vec<Address>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, DefaultJson)]
+pub struct InvalidMove {
+    pub index: usize,
+    pub address: Address,
+    pub reason: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, DefaultJson)]
+pub struct Fork {
+    pub parent: Address,
+    pub children: Vec<Address>,
+}
+
+/// Replay a whole game and report everything which doesn't add up
+pub fn handle_audit_game(game_address: Address) -> ZomeApiResult<AuditReport> {
+    let game = game::get_game(&game_address)?;
+
+    let mut report = AuditReport {
+        game: game_address.clone(),
+        moves: Vec::new(),
+        first_invalid_move: None,
+        forks: Vec::new(),
+        local_only: Vec::new(),
+        missing_locally: Vec::new(),
+    };
+    let mut state = GameState::initial();
+    let mut base = game_address.clone();
+    let mut link_type = "game->move";
+
+    loop {
+        let links = hdk::get_links(&base, LinkMatch::Exactly(link_type), LinkMatch::Any)?.links();
+        let mut children: Vec<Address> = Vec::new();
+        for link in &links {
+            if !children.contains(&link.address) {
+                children.push(link.address.clone());
+            }
+        }
+        if children.len() > 1 {
+            report.forks.push(Fork{parent: base.clone(), children: children.clone()});
+        }
+        let link = match links.into_iter().next() {
+            Some(link) => link,
+            None => break,
+        };
+        // a move can't be linked twice so a loop means the links are corrupt
+        if report.moves.contains(&link.address) {
+            report.first_invalid_move.get_or_insert(InvalidMove {
+                index: report.moves.len(),
+                address: link.address.clone(),
+                reason: "The moves link back to an earlier move".into(),
+            });
+            break
+        }
+
+        let index = report.moves.len();
+        let game_move: Move = hdk::utils::get_as_type(link.address.clone())?;
+        if report.first_invalid_move.is_none() {
+            let checked = check_links(&game_move, &game_address, &base, &link.tag, index)
+                .and_then(|_| game_move.is_valid(game.clone(), state.clone()));
+            match checked {
+                Ok(()) => state = state.evolve(game.clone(), &game_move),
+                Err(reason) => {
+                    report.first_invalid_move = Some(InvalidMove {
+                        index,
+                        address: link.address.clone(),
+                        reason,
+                    });
+                },
+            }
+        }
+
+        report.moves.push(link.address.clone());
+        base = link.address;
+        link_type = "move->move";
+    }
+
+    // compare with the moves of this game the caller holds on their own chain
+    let mut local_moves = Vec::new();
+    for address in hdk::query("move".into(), 0, 0)? {
+        let game_move: Move = hdk::utils::get_as_type(address.clone())?;
+        if game_move.game == game_address {
+            local_moves.push(address);
+        }
+    }
+    report.local_only = local_moves.iter()
+        .filter(|address| !report.moves.contains(address))
+        .cloned()
+        .collect();
+    report.missing_locally = report.moves.iter()
+        .filter(|address| !local_moves.contains(address))
+        .cloned()
+        .collect();
+
+    Ok(report)
+}
+
+// the move points back at the entry it was linked from and the link carries its index
+fn check_links(game_move: &Move, game_address: &Address, base: &Address, tag: &str, index: usize) -> Result<(), String> {
+    if game_move.game != *game_address {
+        return Err("The move belongs to a different game".into())
+    }
+    if game_move.previous_move != *base {
+        return Err("The previous_move of the move is not the entry linked to it".into())
+    }
+    // games from before move links were tagged have empty tags
+    if !tag.is_empty() && tag != index.to_string() {
+        return Err(format!("The link to the move says it is move {}", tag))
+    }
+    Ok(())
+}
@@ -0,0 +1,83 @@
+use hdk::{
+    AGENT_ADDRESS,
+    entry_definition::ValidatingEntryType,
+    error::{ZomeApiResult, ZomeApiError},
+    holochain_persistence_api::{
+        cas::content::Address,
+    },
+    holochain_json_api::{
+        error::JsonError, json::JsonString,
+    },
+    holochain_core_types::{
+        dna::entry_types::Sharing,
+        entry::Entry,
+    }
+};
+
+/// A private record of blocking, or unblocking, an agent. The latest one for an agent wins.
+#[derive(Serialize, Deserialize, Debug, DefaultJson, Clone)]
+pub struct Block {
+    pub agent: Address,
+    pub blocked: bool,
+    pub timestamp: u32,
+}
+
+pub fn handle_block_agent(agent: Address, timestamp: u32) -> ZomeApiResult<Address> {
+    if agent.to_string() == AGENT_ADDRESS.to_string() {
+        return Err(ZomeApiError::Internal("You can't block yourself".into()))
+    }
+    commit_block(Block{agent, blocked: true, timestamp})
+}
+
+pub fn handle_unblock_agent(agent: Address, timestamp: u32) -> ZomeApiResult<Address> {
+    commit_block(Block{agent, blocked: false, timestamp})
+}
+
+fn commit_block(block: Block) -> ZomeApiResult<Address> {
+    let entry = Entry::App(
+        "block".into(),
+        block.into(),
+    );
+    hdk::commit_entry(&entry)
+}
+
+/// Every agent currently blocked by this agent
+pub fn handle_get_blocked_agents() -> ZomeApiResult<Vec<Address>> {
+    let mut blocked: Vec<Address> = Vec::new();
+    // the chain is oldest first so later entries override earlier ones
+    for address in hdk::query("block".into(), 0, 0)? {
+        let block: Block = hdk::utils::get_as_type(address)?;
+        blocked.retain(|agent| *agent != block.agent);
+        if block.blocked {
+            blocked.push(block.agent);
+        }
+    }
+    Ok(blocked)
+}
+
+pub fn is_blocked(agent: &Address) -> ZomeApiResult<bool> {
+    Ok(handle_get_blocked_agents()?.contains(agent))
+}
+
+/// Refuse to start a game, or anything leading to one, with an agent this agent has blocked
+pub fn check_not_blocked(agent: &Address) -> ZomeApiResult<()> {
+    if is_blocked(agent)? {
+        return Err(ZomeApiError::Internal("You have blocked this agent, unblock them to play".into()))
+    }
+    Ok(())
+}
+
+pub fn block_def() -> ValidatingEntryType {
+    entry!(
+        name: "block",
+        description: "A private record of an agent this agent does not want to play",
+        sharing: Sharing::Private,
+        validation_package: || {
+            hdk::ValidationPackageDefinition::Entry
+        },
+        validation: | _validation_data: hdk::EntryValidationData<Block>| {
+            Ok(())
+        },
+        links: []
+    )
+}
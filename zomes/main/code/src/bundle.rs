@@ -0,0 +1,66 @@
+use hdk::{
+    error::{ZomeApiResult, ZomeApiError},
+    holochain_persistence_api::{
+        cas::content::{AddressableContent, Address},
+    },
+    holochain_core_types::{
+        entry::Entry,
+        signature::Provenance,
+        time::Timeout,
+    },
+    holochain_wasm_utils::api_serialization::get_entry::{
+        GetEntryOptions, GetEntryResultType, StatusRequestKind,
+    },
+};
+
+use crate::game;
+use crate::GameState;
+
+pub use rules::bundle::{GameBundle, BundledMove, BundleVerification};
+
+pub fn handle_export_game_bundle(game_address: Address) -> ZomeApiResult<GameBundle> {
+    let game = game::get_game(&game_address)?;
+    let mut moves = Vec::new();
+    for (index, game_move) in game::get_moves(&game_address)?.into_iter().enumerate() {
+        let address = Entry::App("move".into(), game_move.clone().into()).address();
+        // a bundle without signatures could never be verified
+        let signatures = get_signatures(&address)?;
+        if signatures.is_empty() {
+            return Err(ZomeApiError::Internal(format!("Could not find the signatures of move {}", index)))
+        }
+        moves.push(BundledMove {
+            signatures,
+            address,
+            entry: game_move,
+        });
+    }
+    let final_state = moves.iter().fold(GameState::initial(), |state, bundled| state.evolve(game.clone(), &bundled.entry));
+    Ok(GameBundle {
+        game_address,
+        game,
+        moves,
+        final_state,
+    })
+}
+
+/// Re-hash every entry, check the authors' signatures and replay the rules
+pub fn handle_verify_game_bundle(bundle: GameBundle) -> ZomeApiResult<BundleVerification> {
+    Ok(rules::bundle::verify_bundle(&bundle, |provenance, data| {
+        hdk::verify_signature(provenance.clone(), data.to_string()).unwrap_or(false)
+    }))
+}
+
+fn get_signatures(address: &Address) -> ZomeApiResult<Vec<Provenance>> {
+    let options = GetEntryOptions {
+        status_request: StatusRequestKind::Latest,
+        entry: false,
+        headers: true,
+        timeout: Timeout::default(),
+    };
+    match hdk::get_entry_result(address, options)?.result {
+        GetEntryResultType::Single(item) => Ok(
+            item.headers.iter().flat_map(|header| header.provenances().clone()).collect()
+        ),
+        _ => Ok(Vec::new()),
+    }
+}
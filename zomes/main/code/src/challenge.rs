@@ -0,0 +1,200 @@
+use hdk::{
+    AGENT_ADDRESS,
+    entry_definition::ValidatingEntryType,
+    error::{ZomeApiResult, ZomeApiError},
+    holochain_persistence_api::{
+        cas::content::{AddressableContent, Address},
+    },
+    holochain_json_api::{
+        error::JsonError, json::JsonString,
+    },
+    holochain_core_types::{
+        dna::entry_types::Sharing,
+        validation::EntryValidationData,
+        entry::Entry,
+        link::LinkMatch,
+    }
+};
+
+use crate::block;
+use crate::game::{self, Game, FirstMove, TimeControl, DEFAULT_INACTIVITY_WINDOW};
+use crate::matchmaking::GetResponse;
+use crate::links::{LinkChange, load_linked};
+
+/// A challenge is an invitation sent to one specific agent.
+/// No game exists until the challenged agent accepts it.
+#[derive(Serialize, Deserialize, Debug, DefaultJson, Clone)]
+pub struct Challenge {
+    pub challenger: Address,
+    pub opponent: Address,
+    pub message: String,
+    pub created_at: u32,
+}
+
+pub fn handle_create_challenge(opponent: Address, message: String, created_at: u32) -> ZomeApiResult<Address> {
+    block::check_not_blocked(&opponent)?;
+    let challenge = Challenge {
+        challenger: AGENT_ADDRESS.to_string().into(),
+        opponent: opponent.clone(),
+        message,
+        created_at,
+    };
+    let entry = Entry::App(
+        "challenge".into(),
+        challenge.into(),
+    );
+    let challenge_addr = hdk::commit_entry(&entry)?;
+
+    // link from the challenged agent so they can find it
+    hdk::link_entries(
+        &opponent,
+        &challenge_addr,
+        "incoming_challenge",
+        ""
+    )?;
+    Ok(challenge_addr)
+}
+
+/// The open challenges sent to this agent by agents it hasn't blocked. Accepting or declining
+/// a challenge removes its incoming_challenge link so only that one link type needs following.
+pub fn handle_get_incoming_challenges() -> ZomeApiResult<Vec<GetResponse<Challenge>>> {
+    let agent_address: Address = AGENT_ADDRESS.to_string().into();
+    let blocked = block::handle_get_blocked_agents()?;
+    Ok(hdk::utils::get_links_and_load_type(
+        &agent_address,
+        LinkMatch::Exactly("incoming_challenge"),
+        LinkMatch::Any
+    )?.into_iter().filter(|challenge: &Challenge| !blocked.contains(&challenge.challenger)).map(|challenge: Challenge| {
+        let address = Entry::App("challenge".into(), challenge.clone().into()).address();
+        GetResponse{entry: challenge, address}
+    }).collect())
+}
+
+pub fn handle_accept_challenge(challenge_addr: Address, created_at: u32, first_move: FirstMove) -> ZomeApiResult<Address> {
+    let challenge = get_own_challenge(&challenge_addr)?;
+    block::check_not_blocked(&challenge.challenger)?;
+
+    // the challenged agent commits the game, this is their opt-in
+    let me: Address = AGENT_ADDRESS.to_string().into();
+    let game = Game {
+        player_1: me.clone(),
+        player_2: challenge.challenger.clone(),
+        created_at,
+        first_player: first_move.first_player(&me, &challenge.challenger),
+        time_control: TimeControl::Unlimited,
+        inactivity_window: DEFAULT_INACTIVITY_WINDOW,
+    };
+    let game_addr = game::commit_game(game)?;
+
+    hdk::link_entries(
+        &challenge_addr,
+        &game_addr,
+        "from_challenge",
+        ""
+    )?;
+    hdk::remove_link(
+        &challenge.opponent,
+        &challenge_addr,
+        "incoming_challenge",
+        ""
+    )?;
+    Ok(game_addr)
+}
+
+pub fn handle_decline_challenge(challenge_addr: Address) -> ZomeApiResult<()> {
+    let challenge = get_own_challenge(&challenge_addr)?;
+    hdk::remove_link(
+        &challenge.opponent,
+        &challenge_addr,
+        "incoming_challenge",
+        ""
+    )
+}
+
+pub fn handle_check_challenge(challenge_addr: Address) -> ZomeApiResult<Vec<GetResponse<Game>>> {
+    Ok(
+        hdk::utils::get_links_and_load_type(&challenge_addr, LinkMatch::Exactly("from_challenge"), LinkMatch::Any)?
+        .into_iter().map(|game: Game| {
+            let address = Entry::App("game".into(), game.clone().into()).address();
+            GetResponse{entry: game, address}
+        }).collect()
+    )
+}
+
+// load a challenge and make sure it was addressed to this agent
+fn get_own_challenge(challenge_addr: &Address) -> ZomeApiResult<Challenge> {
+    let challenge: Challenge = hdk::utils::get_as_type(challenge_addr.clone())?;
+    if challenge.opponent.to_string() != AGENT_ADDRESS.to_string() {
+        return Err(ZomeApiError::Internal("This challenge was not addressed to you".into()))
+    }
+    Ok(challenge)
+}
+
+// the game is between exactly these two agents, in either order
+fn same_players(game: &Game, a: &Address, b: &Address) -> bool {
+    (game.player_1 == *a && game.player_2 == *b) || (game.player_1 == *b && game.player_2 == *a)
+}
+
+pub fn challenge_def() -> ValidatingEntryType {
+    entry!(
+        name: "challenge",
+        description: "Represents an agent inviting one specific agent to play a game",
+        sharing: Sharing::Public,
+        validation_package: || {
+            hdk::ValidationPackageDefinition::Entry
+        },
+        validation: | validation_data: hdk::EntryValidationData<Challenge>| {
+            match validation_data {
+                EntryValidationData::Create{ entry, validation_data } => {
+                    let challenge = Challenge::from(entry);
+                    if !validation_data.sources().contains(&challenge.challenger) {
+                        return Err("Cannot author a challenge from another agent".into())
+                    }
+                    if challenge.challenger == challenge.opponent {
+                        return Err("Cannot challenge yourself".into())
+                    }
+                    Ok(())
+                },
+                _ => {
+                    Err("Cannot modify or delete a challenge".into())
+                }
+            }
+        },
+        links: [
+            from!(
+                "%agent_id",
+                link_type: "incoming_challenge",
+                validation_package: || { hdk::ValidationPackageDefinition::Entry },
+                validation: | validation_data: hdk::LinkValidationData| {
+                    let link = LinkChange::from_validation_data(validation_data);
+                    let challenge: Challenge = load_linked(&link.target, "challenge")?;
+                    if link.base != challenge.opponent {
+                        return Err("A challenge can only be linked from the agent it challenges".into())
+                    }
+                    // the challenger sends it, the challenged agent clears it by accepting or declining
+                    if !link.authored_by_one_of(&[&challenge.challenger, &challenge.opponent]) {
+                        return Err("Only the players of a challenge can link it".into())
+                    }
+                    Ok(())
+                }
+            ),
+            to!(
+                "game",
+                link_type: "from_challenge",
+                validation_package: || { hdk::ValidationPackageDefinition::Entry },
+                validation: | validation_data: hdk::LinkValidationData| {
+                    let link = LinkChange::from_validation_data(validation_data);
+                    let challenge: Challenge = load_linked(&link.base, "challenge")?;
+                    let game: Game = load_linked(&link.target, "game")?;
+                    if !link.authored_by_one_of(&[&challenge.challenger, &challenge.opponent]) {
+                        return Err("Only the players of a challenge can link a game to it".into())
+                    }
+                    if !same_players(&game, &challenge.challenger, &challenge.opponent) {
+                        return Err("The game must be between the players of the challenge".into())
+                    }
+                    Ok(())
+                }
+            )
+        ]
+    )
+}
@@ -0,0 +1,98 @@
+use hdk::{
+    AGENT_ADDRESS,
+    entry_definition::ValidatingEntryType,
+    error::ZomeApiResult,
+    holochain_persistence_api::{
+        cas::content::Address,
+    },
+    holochain_json_api::{
+        error::JsonError, json::JsonString,
+    },
+    holochain_core_types::{
+        dna::entry_types::Sharing,
+        validation::EntryValidationData,
+        entry::Entry,
+        link::LinkMatch,
+    }
+};
+
+const MAX_MESSAGE_LENGTH: usize = 500;
+
+/// A chat message posted in a game, by one of its players or by a spectator
+#[derive(Serialize, Deserialize, Debug, DefaultJson, Clone)]
+pub struct GameMessage {
+    pub game: Address,
+    pub author: Address,
+    pub text: String,
+    pub timestamp: u32,
+}
+
+pub fn handle_send_game_message(game: Address, text: String, timestamp: u32) -> ZomeApiResult<Address> {
+    let message = GameMessage {
+        game: game.clone(),
+        author: AGENT_ADDRESS.to_string().into(),
+        text,
+        timestamp,
+    };
+    let entry = Entry::App(
+        "game_message".into(),
+        message.into(),
+    );
+    let message_addr = hdk::commit_entry(&entry)?;
+    hdk::link_entries(&game, &message_addr, "game->message", "")?;
+    Ok(message_addr)
+}
+
+/// All messages in a game, oldest first
+pub fn handle_get_game_messages(game: Address) -> ZomeApiResult<Vec<GameMessage>> {
+    let mut messages: Vec<GameMessage> = hdk::utils::get_links_and_load_type(
+        &game,
+        LinkMatch::Exactly("game->message"),
+        LinkMatch::Any
+    )?;
+    messages.sort_by(|a, b| {
+        (a.timestamp, a.author.to_string()).cmp(&(b.timestamp, b.author.to_string()))
+    });
+    Ok(messages)
+}
+
+pub fn game_message_def() -> ValidatingEntryType {
+    entry!(
+        name: "game_message",
+        description: "A chat message posted in a game",
+        sharing: Sharing::Public,
+        validation_package: || {
+            hdk::ValidationPackageDefinition::Entry
+        },
+        validation: | validation_data: hdk::EntryValidationData<GameMessage>| {
+            match validation_data {
+                EntryValidationData::Create{ entry, validation_data } => {
+                    let message = GameMessage::from(entry);
+                    if !validation_data.sources().contains(&message.author) {
+                        return Err("Cannot post a message as another agent".into())
+                    }
+                    if message.text.trim().is_empty() {
+                        return Err("A message can't be empty".into())
+                    }
+                    if message.text.chars().count() > MAX_MESSAGE_LENGTH {
+                        return Err(format!("A message can't be longer than {} characters", MAX_MESSAGE_LENGTH))
+                    }
+                    Ok(())
+                },
+                _ => {
+                    Err("Cannot modify or delete a message".into())
+                }
+            }
+        },
+        links: [
+            from!(
+                "game",
+                link_type: "game->message",
+                validation_package: || { hdk::ValidationPackageDefinition::Entry },
+                validation: | _validation_data: hdk::LinkValidationData| {
+                    Ok(())
+                }
+            )
+        ]
+    )
+}
@@ -0,0 +1,62 @@
+use hdk::{
+    entry_definition::ValidatingEntryType,
+    error::ZomeApiResult,
+    holochain_persistence_api::{
+        cas::content::Address,
+    },
+    holochain_json_api::{
+        error::JsonError, json::JsonString,
+    },
+    holochain_core_types::{
+        dna::entry_types::Sharing,
+        entry::Entry,
+    }
+};
+
+use crate::coin_commitment;
+
+/// A private copy of the secret this agent committed to in a game's coin flip, kept until it is revealed
+#[derive(Serialize, Deserialize, Debug, DefaultJson, Clone)]
+pub struct CoinSecret {
+    pub game: Address,
+    pub secret: String,
+}
+
+/// Keep a secret for the coin flip in a game and return the commitment to make for it.
+/// If a secret was already kept for the game that one is used instead so the commitment never changes.
+pub fn handle_save_coin_secret(game: Address, secret: String) -> ZomeApiResult<Address> {
+    if let Some(saved) = handle_get_coin_secret(game.clone())? {
+        return Ok(coin_commitment(&saved))
+    }
+    let entry = Entry::App(
+        "saved_coin_secret".into(),
+        CoinSecret{game, secret: secret.clone()}.into(),
+    );
+    hdk::commit_entry(&entry)?;
+    Ok(coin_commitment(&secret))
+}
+
+pub fn handle_get_coin_secret(game: Address) -> ZomeApiResult<Option<String>> {
+    for address in hdk::query("saved_coin_secret".into(), 0, 0)? {
+        let saved: CoinSecret = hdk::utils::get_as_type(address)?;
+        if saved.game == game {
+            return Ok(Some(saved.secret))
+        }
+    }
+    Ok(None)
+}
+
+pub fn coin_secret_def() -> ValidatingEntryType {
+    entry!(
+        name: "saved_coin_secret",
+        description: "A private record of the secret an agent committed to in a coin flip",
+        sharing: Sharing::Private,
+        validation_package: || {
+            hdk::ValidationPackageDefinition::Entry
+        },
+        validation: | _validation_data: hdk::EntryValidationData<CoinSecret>| {
+            Ok(())
+        },
+        links: []
+    )
+}
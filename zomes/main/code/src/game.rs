@@ -1,5 +1,6 @@
 use std::convert::TryFrom;
 use hdk::{
+    AGENT_ADDRESS,
     utils,
     entry_definition::ValidatingEntryType,
     error::{ZomeApiResult, ZomeApiError},
@@ -18,27 +19,159 @@ use hdk::{
 };
 
 use crate::game_move::Move;
-use crate::GameState;
+use crate::{GameState, Outcome, whose_turn};
 
+pub use rules::game::{Game, FirstMove, TimeControl, DEFAULT_INACTIVITY_WINDOW};
+
+/// A game as seen by one of its players
 #[derive(Clone, Debug, Serialize, Deserialize, DefaultJson)]
-pub struct Game {
-    pub player_1: Address,
-    pub player_2: Address,
+pub struct GameSummary {
+    pub address: Address,
+    pub opponent: Address,
     pub created_at: u32,
+    pub whose_turn: Option<Address>,
+    pub finished: bool,
+    pub winner: Option<Address>,
+    pub outcome: Option<Outcome>,
 }
 
 /*=====================================
 =            DHT Functions            =
 =====================================*/
 
+/// Commit a new game and link it from both players so they can find it again later
+pub fn commit_game(game: Game) -> ZomeApiResult<Address> {
+    let game_entry = Entry::App(
+        "game".into(),
+        game.clone().into(),
+    );
+    let game_address = hdk::commit_entry(&game_entry)?;
+    hdk::link_entries(&game.player_1, &game_address, "agent->game", "")?;
+    hdk::link_entries(&game.player_2, &game_address, "agent->game", "")?;
+    Ok(game_address)
+}
+
+/// The addresses of every game an agent is a player in
+pub fn get_agent_games(agent_address: &Address) -> ZomeApiResult<Vec<Address>> {
+    // a game can be linked more than once if it was committed by both players
+    let mut game_addresses: Vec<Address> = Vec::new();
+    for address in hdk::get_links(agent_address, LinkMatch::Exactly("agent->game"), LinkMatch::Any)?.addresses() {
+        if !game_addresses.contains(&address) {
+            game_addresses.push(address);
+        }
+    }
+    Ok(game_addresses)
+}
+
+/// Summarise every game the current agent is playing or has played
+pub fn get_my_games() -> ZomeApiResult<Vec<GameSummary>> {
+    let agent_address: Address = AGENT_ADDRESS.to_string().into();
+    get_agent_games(&agent_address)?.into_iter().map(|game_address| {
+        let game = get_game(&game_address)?;
+        let state = get_state(&game_address)?;
+        let opponent = if game.player_1 == agent_address {
+            game.player_2.clone()
+        } else {
+            game.player_1.clone()
+        };
+        Ok(GameSummary {
+            address: game_address,
+            opponent,
+            created_at: game.created_at,
+            whose_turn: whose_turn(&game, &state),
+            finished: state.is_finished(),
+            winner: state.winner(),
+            outcome: state.outcome.clone(),
+        })
+    }).collect()
+}
+
+/// A move along with its address and its position in the game
+#[derive(Clone, Debug, Serialize, Deserialize, DefaultJson)]
+pub struct IndexedMove {
+    pub address: Address,
+    pub index: usize,
+    pub entry: Move,
+}
+
+/// The moves made after `last_known`, or all moves if it is None. Only follows the
+/// links past the known move, reading positions from the link tags. If the tags don't
+/// count up one at a time from the known move the positions are worked out from all the moves.
+pub fn get_moves_since(game_address: &Address, last_known: Option<Address>) -> ZomeApiResult<Vec<IndexedMove>> {
+    let (base, link_type, first_index) = match last_known.clone() {
+        Some(known_address) => {
+            let known: Move = utils::get_as_type(known_address.clone())?;
+            if known.game != *game_address {
+                return Err(ZomeApiError::Internal("That move is not part of this game".into()))
+            }
+            match link_index(&known.previous_move, game_address, &known_address)? {
+                Some(known_index) => (known_address, "move->move", known_index + 1),
+                None => return indexed_moves_after(game_address, last_known),
+            }
+        },
+        None => (game_address.clone(), "game->move", 0),
+    };
+
+    match follow_indexed_links(base, link_type, first_index)? {
+        Some(moves) => Ok(moves),
+        None => indexed_moves_after(game_address, last_known),
+    }
+}
+
+// the position on the link to a move from the move or game before it
+fn link_index(previous: &Address, game_address: &Address, move_address: &Address) -> ZomeApiResult<Option<usize>> {
+    let link_type = if previous == game_address { "game->move" } else { "move->move" };
+    Ok(hdk::get_links(previous, LinkMatch::Exactly(link_type), LinkMatch::Any)?.links().into_iter()
+        .find(|link| link.address == *move_address)
+        .and_then(|link| link.tag.parse().ok()))
+}
+
+// None if a link is missing its position or it is not the one expected
+fn follow_indexed_links(mut base: Address, mut link_type: &str, mut next_index: usize) -> ZomeApiResult<Option<Vec<IndexedMove>>> {
+    let mut moves = Vec::new();
+    while let Some(link) = hdk::get_links(&base, LinkMatch::Exactly(link_type), LinkMatch::Any)?.links().into_iter().next() {
+        match link.tag.parse::<usize>() {
+            Ok(index) if index == next_index => (),
+            _ => return Ok(None),
+        }
+        moves.push(IndexedMove {
+            address: link.address.clone(),
+            index: next_index,
+            entry: utils::get_as_type(link.address.clone())?,
+        });
+        base = link.address;
+        link_type = "move->move";
+        next_index += 1;
+    }
+    Ok(Some(moves))
+}
+
+// number every move of the game by walking it from the start
+fn indexed_moves_after(game_address: &Address, last_known: Option<Address>) -> ZomeApiResult<Vec<IndexedMove>> {
+    let moves: Vec<IndexedMove> = get_moves(game_address)?.into_iter().enumerate()
+        .map(|(index, game_move)| IndexedMove {
+            address: Entry::App("move".into(), game_move.clone().into()).address(),
+            index,
+            entry: game_move,
+        })
+        .collect();
+    let skip = match last_known {
+        Some(known_address) => moves.iter().position(|indexed| indexed.address == known_address)
+            .map(|position| position + 1)
+            .ok_or_else(|| ZomeApiError::Internal("That move is not part of this game".into()))?,
+        None => 0,
+    };
+    Ok(moves.into_iter().skip(skip).collect())
+}
+
 /// Traverse the linked list rooted at a game to find all the moves
 pub fn get_moves(game_address: &Address) -> ZomeApiResult<Vec<Move>> {
-    match hdk::get_links(game_address, LinkMatch::Any, LinkMatch::Any)?.addresses().into_iter().next() {
+    match hdk::get_links(game_address, LinkMatch::Exactly("game->move"), LinkMatch::Any)?.addresses().into_iter().next() {
         Some(first_move) => {
             let mut move_addresses = vec![first_move];
             let mut more = true;
             while more {
-                more = match hdk::get_links(move_addresses.last().unwrap(), LinkMatch::Any, LinkMatch::Any)?.addresses().into_iter().next() {
+                more = match hdk::get_links(move_addresses.last().unwrap(), LinkMatch::Exactly("move->move"), LinkMatch::Any)?.addresses().into_iter().next() {
                     Some(addr) => {
                         move_addresses.push(addr.clone());
                         true
@@ -64,6 +197,44 @@ pub fn get_moves(game_address: &Address) -> ZomeApiResult<Vec<Move>> {
     }
 }
 
+/// What `sync_game` had to add to the caller's local chain
+#[derive(Clone, Debug, Serialize, Deserialize, DefaultJson)]
+pub struct SyncReport {
+    pub game_committed: bool,
+    pub moves_committed: Vec<Address>,
+}
+
+/// Bring the caller's local chain up to date with a game's published moves. Moves are
+/// validated against their author's local chain so it must hold the game and every earlier move.
+pub fn sync_game(game_address: &Address, published_moves: &[Move]) -> ZomeApiResult<SyncReport> {
+    let mut report = SyncReport {
+        game_committed: false,
+        moves_committed: Vec::new(),
+    };
+    if !hdk::query("game".into(), 0, 0)?.contains(game_address) {
+        let game_entry = Entry::App("game".into(), get_game(game_address)?.into());
+        hdk::commit_entry(&game_entry)?;
+        report.game_committed = true;
+    }
+
+    // every earlier sync left the chain holding all the moves up to the caller's last one
+    // so only the moves after the newest one already on the chain need committing
+    let chain_moves = hdk::query("move".into(), 0, 0)?;
+    let missing: Vec<Entry> = published_moves.iter()
+        .rev()
+        .map(|game_move| Entry::App("move".into(), game_move.into()))
+        .take_while(|move_entry| !chain_moves.contains(&move_entry.address()))
+        .collect();
+    for move_entry in missing.into_iter().rev() {
+        report.moves_committed.push(hdk::commit_entry(&move_entry)?);
+    }
+    Ok(report)
+}
+
+pub fn handle_sync_game(game_address: Address) -> ZomeApiResult<SyncReport> {
+    sync_game(&game_address, &get_moves(&game_address)?)
+}
+
 pub fn get_state(game_address: &Address) -> ZomeApiResult<GameState> {
     let moves = get_moves(game_address)?;
     let game = get_game(game_address)?;
@@ -71,6 +242,16 @@ pub fn get_state(game_address: &Address) -> ZomeApiResult<GameState> {
     Ok(new_state)
 }
 
+/// The state of a game after only its first `index` moves
+pub fn get_state_at(game_address: &Address, index: usize) -> ZomeApiResult<GameState> {
+    let moves = get_moves(game_address)?;
+    if index > moves.len() {
+        return Err(ZomeApiError::Internal(format!("The game only has {} moves", moves.len())))
+    }
+    let game = get_game(game_address)?;
+    Ok(moves.iter().take(index).fold(GameState::initial(), |state, new_move| state.evolve(game.clone(), new_move)))
+}
+
 pub fn get_game(game_address: &Address) -> ZomeApiResult<Game> {
     utils::get_as_type(game_address.to_owned())
 }
@@ -150,12 +331,40 @@ pub fn definition() -> ValidatingEntryType {
                     if game.player_1 == game.player_2 {
                         return Err("Player 1 and Player 2 must be different agents.".into())
                     }
+                    if let Some(first_player) = &game.first_player {
+                        if *first_player != game.player_1 && *first_player != game.player_2 {
+                            return Err("The first player must be one of the players.".into())
+                        }
+                    }
                     Ok(())
                 },
                 _ => {
                     Err("Cannot modify or delete a game".into())
                 }
             }
-        }
+        },
+
+        links: [
+            from!(
+                "%agent_id",
+                link_type: "agent->game",
+                validation_package: || {
+                    hdk::ValidationPackageDefinition::Entry
+                },
+                validation: | _validation_data: hdk::LinkValidationData| {
+                    Ok(())
+                }
+            ),
+            to!(
+                "game",
+                link_type: "rematch",
+                validation_package: || {
+                    hdk::ValidationPackageDefinition::Entry
+                },
+                validation: | _validation_data: hdk::LinkValidationData| {
+                    Ok(())
+                }
+            )
+        ]
     )
 }
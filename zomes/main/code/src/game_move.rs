@@ -1,38 +1,17 @@
 use hdk::{
     entry_definition::ValidatingEntryType,
-    holochain_persistence_api::{
-        cas::content::{Address},
-    },
-    holochain_json_api::{
-        error::JsonError, json::JsonString,
-    },
     holochain_core_types::{
         dna::entry_types::Sharing,
         validation::EntryValidationData,
         entry::Entry,
     }
 };
+use rules::your_game::validation::is_near_commit_time;
+use rules::time::seconds_since_epoch;
 
-use crate::MoveType;
 use crate::game::{get_game_local_chain, get_state_local_chain};
 
-
-#[derive(Clone, Debug, Serialize, Deserialize, DefaultJson)]
-pub struct MoveInput {
-	pub game: Address,
-	pub move_type: MoveType,
-	pub timestamp: u32,
-}
-
-
-#[derive(Clone, Debug, Serialize, Deserialize, DefaultJson, PartialEq)]
-pub struct Move {
-	pub game: Address,
-	pub author: Address,
-	pub move_type: MoveType,
-	pub previous_move: Address,
-	pub timestamp: u32,
-}
+pub use rules::game_move::{Move, MoveInput};
 
 pub fn definition() -> ValidatingEntryType {
     entry!(
@@ -46,6 +25,8 @@ pub fn definition() -> ValidatingEntryType {
         validation: | validation_data: hdk::EntryValidationData<Move>| {
             match validation_data {
                 EntryValidationData::Create{entry, validation_data} => {
+                	let committed_at = seconds_since_epoch(&validation_data.package.chain_header.timestamp().to_string())?;
+                	let sources = validation_data.sources();
                 	let mut local_chain = validation_data.package.source_chain_entries
                 		.ok_or("Could not retrieve source chain")?;
                 	hdk::debug(format!("{:?}", local_chain))?;
@@ -62,6 +43,7 @@ pub fn definition() -> ValidatingEntryType {
                 	let game = get_game_local_chain(local_chain, &_new_move.game)
                 	    .map_err(|_| "Could not load game during validation")?;
 
+                    is_near_commit_time(_new_move.timestamp, committed_at, sources.contains(&_new_move.author))?;
                     _new_move.is_valid(game, state)
                 },
                 _ => {
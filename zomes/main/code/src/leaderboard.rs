@@ -0,0 +1,140 @@
+use hdk::{
+    error::ZomeApiResult,
+    holochain_persistence_api::{
+        cas::content::{AddressableContent, Address},
+    },
+    holochain_json_api::{
+        error::JsonError, json::JsonString,
+    },
+    holochain_core_types::{
+        link::LinkMatch,
+    }
+};
+
+use crate::links::{LinkChange, load_linked};
+use crate::matchmaking::{anchor_entry, parse_offset_cursor, Page, DEFAULT_PAGE_SIZE};
+use crate::rating::GameResult;
+use crate::Outcome;
+
+const LEADERBOARD_ANCHOR: &str = "leaderboard";
+const WIN_TAG: &str = "win";
+const LOSS_TAG: &str = "loss";
+
+#[derive(Clone, Debug, Serialize, Deserialize, DefaultJson)]
+pub struct LeaderboardEntry {
+    pub agent: Address,
+    pub wins: usize,
+    pub losses: usize,
+    pub games_played: usize,
+    pub win_percentage: f64,
+}
+
+impl LeaderboardEntry {
+    fn new(agent: Address, wins: usize, losses: usize) -> Self {
+        let games_played = wins + losses;
+        LeaderboardEntry {
+            agent,
+            wins,
+            losses,
+            games_played,
+            win_percentage: if games_played == 0 { 0.0 } else { 100.0 * wins as f64 / games_played as f64 },
+        }
+    }
+}
+
+/// Index a result on the leaderboard if it was won by bringing every token home.
+/// The anchor links to each participant and each participant to the result, tagged with how they did.
+pub fn index_result(result: &GameResult, result_address: &Address) -> ZomeApiResult<()> {
+    let winner = match result.outcome {
+        Outcome::Win{ref winner} => winner,
+        _ => return Ok(()),
+    };
+    let anchor_address = hdk::commit_entry(&anchor_entry(LEADERBOARD_ANCHOR))?;
+    for player in vec![&result.player_1, &result.player_2] {
+        let tag = if player == winner { WIN_TAG } else { LOSS_TAG };
+        hdk::link_entries(&anchor_address, player, "ranked_player", &result_address.to_string())?;
+        hdk::link_entries(player, result_address, "ranked_result", tag)?;
+    }
+    Ok(())
+}
+
+/// Rank every agent with an indexed game by wins, then win percentage, then games played
+pub fn handle_get_leaderboard(limit: Option<usize>, cursor: Option<String>) -> ZomeApiResult<Page<LeaderboardEntry>> {
+    let offset = parse_offset_cursor(cursor)?;
+    let limit = limit.unwrap_or(DEFAULT_PAGE_SIZE);
+
+    // each player is linked once per game so drop the repeats
+    let mut players = hdk::get_links(&anchor_entry(LEADERBOARD_ANCHOR).address(), LinkMatch::Exactly("ranked_player"), LinkMatch::Any)?.addresses();
+    players.sort_by(|a, b| a.to_string().cmp(&b.to_string()));
+    players.dedup();
+
+    let mut entries = Vec::new();
+    for player in players {
+        // both players may have indexed the same result
+        let mut games: Vec<(String, String)> = hdk::get_links(&player, LinkMatch::Exactly("ranked_result"), LinkMatch::Any)?
+            .links()
+            .into_iter()
+            .map(|link| (link.address.to_string(), link.tag))
+            .collect();
+        games.sort();
+        games.dedup();
+        let wins = games.iter().filter(|(_, tag)| tag == WIN_TAG).count();
+        let losses = games.iter().filter(|(_, tag)| tag == LOSS_TAG).count();
+        entries.push(LeaderboardEntry::new(player, wins, losses));
+    }
+
+    entries.sort_by(|a, b| {
+        b.wins.cmp(&a.wins)
+            .then(b.win_percentage.partial_cmp(&a.win_percentage).unwrap_or(std::cmp::Ordering::Equal))
+            .then(b.games_played.cmp(&a.games_played))
+            .then(a.agent.to_string().cmp(&b.agent.to_string()))
+    });
+
+    let next_cursor = if offset + limit < entries.len() {
+        Some((offset + limit).to_string())
+    } else {
+        None
+    };
+    let items = entries.into_iter().skip(offset).take(limit).collect();
+    Ok(Page{items, next_cursor})
+}
+
+/// The leaderboard anchor links to a player of a won game, tagged with the address of its result
+pub fn validate_ranked_player(link: LinkChange) -> Result<(), String> {
+    if link.base != anchor_entry(LEADERBOARD_ANCHOR).address() {
+        return Err("Players can only be ranked from the leaderboard anchor".into())
+    }
+    let result: GameResult = load_linked(&link.tag.clone().into(), "game result")?;
+    validate_ranked_link(&link, &result, &link.target)
+}
+
+/// A player links to the result of a won game, tagged with whether they won or lost it
+pub fn validate_ranked_result(link: LinkChange) -> Result<(), String> {
+    let result: GameResult = load_linked(&link.target, "game result")?;
+    validate_ranked_link(&link, &result, &link.base)?;
+    let expected_tag = match result.outcome {
+        Outcome::Win{ref winner} if *winner == link.base => WIN_TAG,
+        _ => LOSS_TAG,
+    };
+    if link.tag != expected_tag {
+        return Err("A ranked result must be tagged with how the player did".into())
+    }
+    Ok(())
+}
+
+fn validate_ranked_link(link: &LinkChange, result: &GameResult, player: &Address) -> Result<(), String> {
+    if !link.adding {
+        return Err("Cannot remove a game from the leaderboard".into())
+    }
+    match result.outcome {
+        Outcome::Win{..} => (),
+        _ => return Err("Only games won by bringing every token home are ranked".into()),
+    }
+    if *player != result.player_1 && *player != result.player_2 {
+        return Err("Only the players of a game can be ranked for it".into())
+    }
+    if !link.authored_by_one_of(&[&result.player_1, &result.player_2]) {
+        return Err("Only the players of a game can rank it".into())
+    }
+    Ok(())
+}
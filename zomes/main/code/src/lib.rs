@@ -14,7 +14,7 @@ use hdk_proc_macros::zome;
 use hdk::{
     AGENT_ADDRESS,
     entry_definition::ValidatingEntryType,
-    error::ZomeApiResult,
+    error::{ZomeApiResult, ZomeApiError},
     holochain_persistence_api::{
         cas::content::{
             Address,
@@ -26,22 +26,54 @@ use hdk::{
     },
 };
 
-// This is where you would import your own game State, MoveType and state_reducer
+extern crate rules;
 
-mod your_game;
+// The game State, MoveType and state_reducer live in the rules crate so the CLI can use them too
+
+use rules::your_game;
 pub use your_game::{
     GameState,
     MoveType,
+    Outcome,
+    whose_turn,
+    coin_commitment,
+    VARIANT,
 };
 
 
 mod game;
 mod game_move;
 mod matchmaking;
-
-use game::Game;
+mod challenge;
+mod queue;
+mod rating;
+mod leaderboard;
+mod tournament;
+mod series;
+mod chat;
+mod notify;
+mod signal;
+mod audit;
+mod bundle;
+mod profile;
+mod block;
+mod coin;
+mod links;
+
+use game::{Game, GameSummary, IndexedMove, SyncReport, FirstMove, TimeControl, DEFAULT_INACTIVITY_WINDOW};
 use game_move::{Move, MoveInput};
-use matchmaking::{GameProposal, GetResponse};
+use matchmaking::{GameProposal, GetResponse, Page};
+use challenge::Challenge;
+use rating::{Rating, GameResult};
+use leaderboard::LeaderboardEntry;
+use tournament::{RoundPairings, Standing, TournamentFormat};
+use series::SeriesState;
+use chat::GameMessage;
+use notify::MoveNotification;
+use signal::GameSignal;
+use audit::AuditReport;
+use bundle::{GameBundle, BundleVerification};
+use profile::Profile;
 
 #[zome]
 pub mod main {
@@ -56,6 +88,11 @@ pub mod main {
         Ok(())
     }
 
+    #[receive]
+    pub fn receive(from: Address, msg_json: String) -> String {
+        notify::handle_receive(from, msg_json)
+    }
+
     /*=========================================
     =            Entry Definitions            =
     =========================================*/
@@ -80,6 +117,61 @@ pub mod main {
         matchmaking::anchor_def()
     }
 
+    #[entry_def]
+    fn challenge_def() -> ValidatingEntryType {
+        challenge::challenge_def()
+    }
+
+    #[entry_def]
+    fn queue_entry_def() -> ValidatingEntryType {
+        queue::queue_entry_def()
+    }
+
+    #[entry_def]
+    fn game_result_def() -> ValidatingEntryType {
+        rating::game_result_def()
+    }
+
+    #[entry_def]
+    fn tournament_def() -> ValidatingEntryType {
+        tournament::tournament_def()
+    }
+
+    #[entry_def]
+    fn tournament_round_def() -> ValidatingEntryType {
+        tournament::tournament_round_def()
+    }
+
+    #[entry_def]
+    fn series_def() -> ValidatingEntryType {
+        series::series_def()
+    }
+
+    #[entry_def]
+    fn game_message_def() -> ValidatingEntryType {
+        chat::game_message_def()
+    }
+
+    #[entry_def]
+    fn move_notification_def() -> ValidatingEntryType {
+        notify::move_notification_def()
+    }
+
+    #[entry_def]
+    fn profile_def() -> ValidatingEntryType {
+        profile::profile_def()
+    }
+
+    #[entry_def]
+    fn block_def() -> ValidatingEntryType {
+        block::block_def()
+    }
+
+    #[entry_def]
+    fn coin_secret_def() -> ValidatingEntryType {
+        coin::coin_secret_def()
+    }
+
     /*=====  End of Entry Definitions  ======*/
 
 
@@ -88,45 +180,36 @@ pub mod main {
     ======================================*/
 
     #[zome_fn("hc_public")]
-    fn create_game(opponent: Address, timestamp: u32) -> ZomeApiResult<Address> {
+    fn create_game(opponent: Address, timestamp: u32, time_control: Option<TimeControl>, inactivity_window: Option<u32>, first_move: Option<FirstMove>) -> ZomeApiResult<Address> {
+        block::check_not_blocked(&opponent)?;
+        let me: Address = AGENT_ADDRESS.to_string().into();
+        // unless asked otherwise the invited player starts
+        let first_player = first_move.unwrap_or_default().first_player(&me, &opponent);
         let new_game = Game {
-            player_1: AGENT_ADDRESS.to_string().into(),
+            player_1: me,
             player_2: opponent,
             created_at: timestamp,
+            first_player,
+            time_control: time_control.unwrap_or(TimeControl::Unlimited),
+            inactivity_window: inactivity_window.unwrap_or(DEFAULT_INACTIVITY_WINDOW),
         };
-        let game_entry = Entry::App(
-            "game".into(),
-            new_game.into(),
-        );
-        hdk::commit_entry(&game_entry)
+        game::commit_game(new_game)
     }
 
+    /// Commit a move and tell the opponent about it directly. If the opponent is offline
+    /// the direct message times out, so a move can take up to half a second longer to return.
     #[zome_fn("hc_public")]
     fn make_move(new_move: MoveInput) -> ZomeApiResult<()> {
         // get all the moves from the DHT by following the hash chain
         let published_moves = game::get_moves(&new_move.game)?;
 
-        // get all moves in this agents local chain
-        let chain_moves = hdk::query("move".into(), 0, 0)?;
-
-        // Update this agents local chain to match the game state
-        let base_address = match published_moves.clone().last() {
-            Some(last_move) => { // add any moves NOT found in the DHT to this agents local chain
-                for _move in &published_moves {
-                    let move_entry = Entry::App("move".into(), _move.into());
-                    if !chain_moves.contains(&move_entry.address()) {
-                        hdk::commit_entry(&move_entry)?;
-                    }
-                }
-                Entry::App("move".into(), last_move.into()).address()
-            }
-
+        // Update this agents local chain to match the game state, validation replays it
+        game::sync_game(&new_move.game, &published_moves)?;
 
-            None => { // no moves have been made so commit the Game to local chain
-                let game = game::get_game(&new_move.game)?;
-                let game_entry = Entry::App("game".into(), game.into());
-                hdk::commit_entry(&game_entry)?
-            }
+        // the new move follows the last published move, or the game if there are none
+        let base_address = match published_moves.last() {
+            Some(last_move) => Entry::App("move".into(), last_move.into()).address(),
+            None => new_move.game.clone(),
         };
 
         let new_move = Move {
@@ -138,32 +221,225 @@ pub mod main {
         };
         let move_entry = Entry::App(
             "move".into(),
-            new_move.into(),
+            new_move.clone().into(),
         );
         let move_address = hdk::commit_entry(&move_entry)?;
 
+        // tag the link with the position of the move so clients can check the order
+        let move_index = published_moves.len().to_string();
         match published_moves.last() {
            Some(_) => {
                // base is a move
-               hdk::link_entries(&base_address, &move_address, "move->move", "")?;
+               hdk::link_entries(&base_address, &move_address, "move->move", move_index.as_str())?;
            }
            None => {
                // base is a game
-               hdk::link_entries(&base_address, &move_address, "game->move", "")?;
+               hdk::link_entries(&base_address, &move_address, "game->move", move_index.as_str())?;
            }
        }
 
+        // tell the opponent straight away, if they are offline they will find the move by polling
+        let game = game::get_game(&new_move.game)?;
+        notify::notify_opponent(&game, &new_move.game, &move_address, &new_move);
+
+        // if this move finished the game record the result. The move is already committed
+        // so failing to record must not fail the move, record_game_result can do it later
+        let state = published_moves.iter()
+            .chain(std::iter::once(&new_move))
+            .fold(GameState::initial(), |state, game_move| state.evolve(game.clone(), game_move));
+        if let Some(outcome) = state.outcome.clone() {
+            if let Err(err) = rating::record_result(&new_move.game, &game, &state) {
+                let _ = hdk::debug(format!("Could not record the result of game {}: {:?}", new_move.game, err));
+            }
+            signal::emit(GameSignal::GameFinished{game: new_move.game.clone(), outcome});
+        }
+
         Ok(())
     }
 
     #[zome_fn("hc_public")]
-    fn get_state(game_address: Address) -> ZomeApiResult<GameState> {
-        game::get_state(&game_address)
+    fn get_state(game_address: Address, timestamp: Option<u32>) -> ZomeApiResult<GameState> {
+        let state = game::get_state(&game_address)?;
+        match timestamp {
+            Some(now) => Ok(state.with_inactivity(&game::get_game(&game_address)?, now)),
+            None => Ok(state),
+        }
+    }
+
+    #[zome_fn("hc_public")]
+    fn render_state(game_address: Address, timestamp: Option<u32>) -> ZomeApiResult<String> {
+        let game = game::get_game(&game_address)?;
+        let mut state = game::get_state(&game_address)?;
+        if let Some(now) = timestamp {
+            state = state.with_inactivity(&game, now);
+        }
+        let mut rendered = state.render();
+        let me = AGENT_ADDRESS.to_string();
+        let decided_by = if game.first_player.is_some() { "the game" } else { "coin flip" };
+        match state.first_player(&game) {
+            Some(ref first) if first.to_string() == me => rendered.push_str(&format!("\nYou move first (decided by {})", decided_by)),
+            Some(_) => rendered.push_str(&format!("\nYour opponent moves first (decided by {})", decided_by)),
+            None => rendered.push_str("\nWaiting for the coin flip to decide who moves first"),
+        }
+        if let Some(inactivity) = &state.inactive {
+            if inactivity.player.to_string() == me {
+                rendered.push_str(&format!("\nYou have been inactive for {} days", inactivity.days));
+            } else {
+                rendered.push_str(&format!("\nOpponent inactive for {} days", inactivity.days));
+                if inactivity.can_claim {
+                    rendered.push_str("\nYou can claim this game with the ClaimAbandoned move");
+                }
+            }
+        }
+        Ok(rendered)
+    }
+
+    /// The whole game written out in Ur notation
+    #[zome_fn("hc_public")]
+    fn export_game(game_address: Address) -> ZomeApiResult<String> {
+        let game = game::get_game(&game_address)?;
+        let moves = game::get_moves(&game_address)?;
+        Ok(your_game::notation::to_notation(&game_address, &game, &moves))
+    }
+
+    /// Check a game written in Ur notation by replaying it, returning the state it ends in
+    #[zome_fn("hc_public")]
+    fn replay_notation(notation: String) -> ZomeApiResult<GameState> {
+        your_game::notation::from_notation(&notation)
+            .map(|(_, _, state)| state)
+            .map_err(ZomeApiError::Internal)
+    }
+
+    /// A self-contained copy of a game, with the signatures of its moves, for keeping outside the DHT
+    #[zome_fn("hc_public")]
+    fn export_game_bundle(game_address: Address) -> ZomeApiResult<GameBundle> {
+        bundle::handle_export_game_bundle(game_address)
+    }
+
+    /// Check an exported game without looking anything up on the DHT
+    #[zome_fn("hc_public")]
+    fn verify_game_bundle(bundle: GameBundle) -> ZomeApiResult<BundleVerification> {
+        bundle::handle_verify_game_bundle(bundle)
+    }
+
+    /// Commit any of a game's published moves which are missing from this agent's local chain
+    #[zome_fn("hc_public")]
+    fn sync_game(game_address: Address) -> ZomeApiResult<SyncReport> {
+        game::handle_sync_game(game_address)
+    }
+
+    /// Replay a game from the DHT and report invalid moves, forks and differences with the local chain
+    #[zome_fn("hc_public")]
+    fn audit_game(game_address: Address) -> ZomeApiResult<AuditReport> {
+        audit::handle_audit_game(game_address)
+    }
+
+    /// Only the moves made after the last one a client already has
+    #[zome_fn("hc_public")]
+    fn get_moves_since(game_address: Address, last_known_move_address: Option<Address>) -> ZomeApiResult<Vec<IndexedMove>> {
+        game::get_moves_since(&game_address, last_known_move_address)
+    }
+
+    #[zome_fn("hc_public")]
+    fn get_state_at(game_address: Address, index: usize) -> ZomeApiResult<GameState> {
+        game::get_state_at(&game_address, index)
+    }
+
+    /// The board after the first `index` moves along with the move that produced it
+    #[zome_fn("hc_public")]
+    fn render_state_at(game_address: Address, index: usize) -> ZomeApiResult<String> {
+        let game = game::get_game(&game_address)?;
+        let state = game::get_state_at(&game_address, index)?;
+        let mut rendered = state.render();
+        match state.moves.last() {
+            Some(last_move) => {
+                let before = game::get_state_at(&game_address, index - 1)?;
+                rendered.push_str(&format!("\nMove {}: {}", index, your_game::notation::move_text(&game, &before, last_move)));
+            },
+            None => rendered.push_str("\nStart of the game"),
+        }
+        Ok(rendered)
+    }
+
+    #[zome_fn("hc_public")]
+    fn rematch(game_address: Address) -> ZomeApiResult<Address> {
+        series::handle_rematch(game_address)
+    }
+
+    #[zome_fn("hc_public")]
+    fn create_series(opponent: Address, best_of: u32, time_control: Option<TimeControl>, timestamp: u32) -> ZomeApiResult<Address> {
+        series::handle_create_series(opponent, best_of, time_control.unwrap_or(TimeControl::Unlimited), timestamp)
+    }
+
+    #[zome_fn("hc_public")]
+    fn get_series_state(series_address: Address) -> ZomeApiResult<SeriesState> {
+        series::handle_get_series_state(series_address)
+    }
+
+    /// Moves opponents have told this agent about directly
+    #[zome_fn("hc_public")]
+    fn get_notifications() -> ZomeApiResult<Vec<MoveNotification>> {
+        notify::handle_get_notifications()
+    }
+
+    #[zome_fn("hc_public")]
+    fn send_game_message(game_address: Address, text: String, timestamp: u32) -> ZomeApiResult<Address> {
+        chat::handle_send_game_message(game_address, text, timestamp)
+    }
+
+    #[zome_fn("hc_public")]
+    fn get_game_messages(game_address: Address) -> ZomeApiResult<Vec<GameMessage>> {
+        chat::handle_get_game_messages(game_address)
+    }
+
+    #[zome_fn("hc_public")]
+    fn get_my_games() -> ZomeApiResult<Vec<GameSummary>> {
+        game::get_my_games()
     }
 
     #[zome_fn("hc_public")]
-    fn render_state(game_address: Address) -> ZomeApiResult<String> {
-        Ok(game::get_state(&game_address)?.render())
+    fn get_rating(agent: Address) -> ZomeApiResult<Rating> {
+        rating::handle_get_rating(agent)
+    }
+
+    #[zome_fn("hc_public")]
+    fn record_game_result(result: GameResult) -> ZomeApiResult<Address> {
+        rating::handle_record_game_result(result)
+    }
+
+    #[zome_fn("hc_public")]
+    fn get_leaderboard(limit: Option<usize>, cursor: Option<String>) -> ZomeApiResult<Page<LeaderboardEntry>> {
+        leaderboard::handle_get_leaderboard(limit, cursor)
+    }
+
+    #[zome_fn("hc_public")]
+    fn set_profile(nickname: String, bio: String, preferred_variant: Option<String>, timestamp: u32) -> ZomeApiResult<Address> {
+        profile::handle_set_profile(nickname, bio, preferred_variant, timestamp)
+    }
+
+    #[zome_fn("hc_public")]
+    fn get_profile(agent: Address) -> ZomeApiResult<Option<Profile>> {
+        profile::handle_get_profile(agent)
+    }
+
+    #[zome_fn("hc_public")]
+    fn get_agent_directory(limit: Option<usize>, cursor: Option<String>) -> ZomeApiResult<Page<Profile>> {
+        profile::handle_get_agent_directory(limit, cursor)
+    }
+
+    #[zome_fn("hc_public")]
+    fn block_agent(agent: Address, timestamp: u32) -> ZomeApiResult<Address> {
+        block::handle_block_agent(agent, timestamp)
+    }
+
+    #[zome_fn("hc_public")]
+    fn unblock_agent(agent: Address, timestamp: u32) -> ZomeApiResult<Address> {
+        block::handle_unblock_agent(agent, timestamp)
+    }
+
+    #[zome_fn("hc_public")]
+    fn get_blocked_agents() -> ZomeApiResult<Vec<Address>> {
+        block::handle_get_blocked_agents()
     }
 
     #[zome_fn("hc_public")]
@@ -171,34 +447,121 @@ pub mod main {
         Ok(MoveType::describe())
     }
 
+    /// The commitment to make with a CommitCoin move for a secret only this agent knows
+    #[zome_fn("hc_public")]
+    fn get_coin_commitment(secret: String) -> ZomeApiResult<Address> {
+        Ok(coin_commitment(&secret))
+    }
+
+    /// Keep a coin flip secret on this agent's private chain and get the commitment for it
+    #[zome_fn("hc_public")]
+    fn save_coin_secret(game_address: Address, secret: String) -> ZomeApiResult<Address> {
+        coin::handle_save_coin_secret(game_address, secret)
+    }
+
+    #[zome_fn("hc_public")]
+    fn get_coin_secret(game_address: Address) -> ZomeApiResult<Option<String>> {
+        coin::handle_get_coin_secret(game_address)
+    }
+
     #[zome_fn("hc_public")]
     fn whoami() -> ZomeApiResult<Address> {
         Ok(AGENT_ADDRESS.to_string().into())
     }
 
     #[zome_fn("hc_public")]
-    fn create_proposal(message: String) -> ZomeApiResult<Address> {
-        matchmaking::handle_create_proposal(message)
+    fn create_proposal(message: String, timestamp: u32) -> ZomeApiResult<Address> {
+        matchmaking::handle_create_proposal(message, timestamp)
     }
 
     #[zome_fn("hc_public")]
-    fn get_proposals() -> ZomeApiResult<Vec<GetResponse<GameProposal>>> {
-        matchmaking::handle_get_proposals()
+    fn get_proposals(timestamp: u32, cursor: Option<String>, limit: Option<usize>) -> ZomeApiResult<Page<GetResponse<GameProposal>>> {
+        matchmaking::handle_get_proposals(timestamp, cursor, limit)
     }
 
     #[zome_fn("hc_public")]
-    fn accept_proposal(proposal_addr: Address, created_at: u32) -> ZomeApiResult<Address> {
-        matchmaking::handle_accept_proposal(proposal_addr, created_at)
+    fn accept_proposal(proposal_addr: Address, created_at: u32, first_move: Option<FirstMove>) -> ZomeApiResult<Address> {
+        matchmaking::handle_accept_proposal(proposal_addr, created_at, first_move.unwrap_or_default())
     }
 
     #[zome_fn("hc_public")]
-    fn check_responses(proposal_addr: Address) -> ZomeApiResult<Vec<GetResponse<Game>>> {
-        matchmaking::handle_check_responses(proposal_addr)
+    fn check_responses(proposal_addr: Address, cursor: Option<String>, limit: Option<usize>) -> ZomeApiResult<Page<GetResponse<Game>>> {
+        matchmaking::handle_check_responses(proposal_addr, cursor, limit)
     }
 
     #[zome_fn("hc_public")]
     fn remove_proposal(proposal_addr: Address) -> ZomeApiResult<Address> {
         matchmaking::handle_remove_proposal(proposal_addr)
     }
+
+    #[zome_fn("hc_public")]
+    fn create_challenge(opponent: Address, message: String, timestamp: u32) -> ZomeApiResult<Address> {
+        challenge::handle_create_challenge(opponent, message, timestamp)
+    }
+
+    #[zome_fn("hc_public")]
+    fn get_incoming_challenges() -> ZomeApiResult<Vec<GetResponse<Challenge>>> {
+        challenge::handle_get_incoming_challenges()
+    }
+
+    #[zome_fn("hc_public")]
+    fn accept_challenge(challenge_addr: Address, created_at: u32, first_move: Option<FirstMove>) -> ZomeApiResult<Address> {
+        challenge::handle_accept_challenge(challenge_addr, created_at, first_move.unwrap_or_default())
+    }
+
+    #[zome_fn("hc_public")]
+    fn decline_challenge(challenge_addr: Address) -> ZomeApiResult<()> {
+        challenge::handle_decline_challenge(challenge_addr)
+    }
+
+    #[zome_fn("hc_public")]
+    fn check_challenge(challenge_addr: Address) -> ZomeApiResult<Vec<GetResponse<Game>>> {
+        challenge::handle_check_challenge(challenge_addr)
+    }
+
+    #[zome_fn("hc_public")]
+    fn join_queue(timestamp: u32) -> ZomeApiResult<Address> {
+        queue::handle_join_queue(timestamp)
+    }
+
+    #[zome_fn("hc_public")]
+    fn leave_queue() -> ZomeApiResult<()> {
+        queue::handle_leave_queue()
+    }
+
+    #[zome_fn("hc_public")]
+    fn find_match(timestamp: u32) -> ZomeApiResult<Option<Address>> {
+        queue::handle_find_match(timestamp)
+    }
+
+    #[zome_fn("hc_public")]
+    fn create_tournament(name: String, participants: Vec<Address>, format: TournamentFormat, rounds: u32, variant: String, timestamp: u32) -> ZomeApiResult<Address> {
+        tournament::handle_create_tournament(name, participants, format, rounds, variant, timestamp)
+    }
+
+    #[zome_fn("hc_public")]
+    fn register_for_tournament(tournament_addr: Address) -> ZomeApiResult<()> {
+        tournament::handle_register(tournament_addr)
+    }
+
+    #[zome_fn("hc_public")]
+    fn get_pairings(tournament_addr: Address, round: u32) -> ZomeApiResult<RoundPairings> {
+        tournament::handle_get_pairings(tournament_addr, round)
+    }
+
+    #[zome_fn("hc_public")]
+    fn start_round(tournament_addr: Address, round: u32) -> ZomeApiResult<Address> {
+        tournament::handle_start_round(tournament_addr, round)
+    }
+
+    #[zome_fn("hc_public")]
+    fn record_tournament_result(tournament_addr: Address, game_addr: Address) -> ZomeApiResult<Address> {
+        tournament::handle_record_result(tournament_addr, game_addr)
+    }
+
+    #[zome_fn("hc_public")]
+    fn get_standings(tournament_addr: Address) -> ZomeApiResult<Vec<Standing>> {
+        tournament::handle_get_standings(tournament_addr)
+    }
     /*=====  End of Zome functions  ======*/
 }
@@ -0,0 +1,45 @@
+use std::convert::TryFrom;
+use hdk::{
+    holochain_persistence_api::{
+        cas::content::Address,
+    },
+    holochain_json_api::{
+        json::JsonString,
+    },
+};
+
+/// What link validation needs to know about a link, whether it is being added or removed
+pub struct LinkChange {
+    pub base: Address,
+    pub target: Address,
+    pub tag: String,
+    pub sources: Vec<Address>,
+    pub adding: bool,
+}
+
+impl LinkChange {
+    pub fn from_validation_data(validation_data: hdk::LinkValidationData) -> LinkChange {
+        let (link, validation_data, adding) = match validation_data {
+            hdk::LinkValidationData::LinkAdd{link, validation_data} => (link, validation_data, true),
+            hdk::LinkValidationData::LinkRemove{link, validation_data} => (link, validation_data, false),
+        };
+        LinkChange {
+            base: link.link().base().clone(),
+            target: link.link().target().clone(),
+            tag: link.link().tag().clone(),
+            sources: validation_data.sources(),
+            adding,
+        }
+    }
+
+    /// Whether the link was made, or removed, by one of these agents
+    pub fn authored_by_one_of(&self, agents: &[&Address]) -> bool {
+        agents.iter().any(|agent| self.sources.contains(agent))
+    }
+}
+
+/// Load an entry a link points to or from while validating the link
+pub fn load_linked<T: TryFrom<JsonString>>(address: &Address, what: &str) -> Result<T, String> {
+    hdk::utils::get_as_type(address.clone())
+        .map_err(|_| format!("Could not load the linked {} during validation", what))
+}
@@ -1,7 +1,7 @@
 use hdk::{
     AGENT_ADDRESS,
     entry_definition::ValidatingEntryType,
-    error::ZomeApiResult,
+    error::{ZomeApiResult, ZomeApiError},
     holochain_persistence_api::{
         cas::content::{AddressableContent, Address},
     },
@@ -17,14 +17,30 @@ use hdk::{
 };
 
 use serde::Serialize;
+use std::convert::TryFrom;
 use std::fmt::Debug;
 
-use crate::game::Game;
+use crate::game::{self, Game, FirstMove, TimeControl, DEFAULT_INACTIVITY_WINDOW};
+use crate::block;
+use crate::leaderboard;
+use crate::links::LinkChange;
+use crate::notify;
+use crate::signal::{self, GameSignal};
+
+/// Proposals are sharded across one anchor per bucket of this many seconds
+const PROPOSAL_BUCKET_SECONDS: u32 = 60 * 60;
+/// How many buckets a single call to get_proposals will look through
+const BUCKETS_PER_PAGE: u32 = 6;
+/// Proposals older than this many buckets are not considered current
+const RECENT_BUCKETS: u32 = 48;
+/// Page size used when the caller does not give a limit
+pub const DEFAULT_PAGE_SIZE: usize = 20;
 
 #[derive(Serialize, Deserialize, Debug, DefaultJson, Clone)]
 pub struct GameProposal {
     pub agent: Address,
     pub message: String,
+    pub created_at: u32,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -39,12 +55,76 @@ impl<T: Into<JsonString> + Debug + Serialize> From<GetResponse<T>> for JsonStrin
     }
 } 
 
-pub fn handle_create_proposal(message: String) -> ZomeApiResult<Address> {
+/// One page of results. Pass `next_cursor` back in to get the following page,
+/// it is None once there is nothing more to read.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub next_cursor: Option<String>,
+}
+
+impl<T: Into<JsonString> + Debug + Serialize> From<Page<T>> for JsonString {
+    fn from(u: Page<T>) -> JsonString {
+        default_to_json(u)
+    }
+}
+
+/// Load up to `limit` of the entries linked from `base`, skipping the first `offset` links.
+/// Entries which can't be loaded, such as removed proposals, or which `keep` rejects are skipped.
+/// Also returns the offset to continue from if there are more links.
+pub fn get_links_page<T: TryFrom<JsonString>, F: Fn(&T) -> bool>(
+    base: &Address,
+    link_type: &str,
+    offset: usize,
+    limit: usize,
+    keep: F,
+) -> ZomeApiResult<(Vec<GetResponse<T>>, Option<usize>)> {
+    let addresses = hdk::get_links(base, LinkMatch::Exactly(link_type), LinkMatch::Any)?.addresses();
+    let mut items = Vec::new();
+    let mut next = offset;
+    while next < addresses.len() && items.len() < limit {
+        let address = addresses[next].clone();
+        next += 1;
+        match hdk::utils::get_as_type::<T>(address.clone()) {
+            Ok(ref entry) if !keep(entry) => (),
+            Ok(entry) => items.push(GetResponse{entry, address}),
+            Err(_) => (),
+        }
+    }
+    let next_offset = if next < addresses.len() { Some(next) } else { None };
+    Ok((items, next_offset))
+}
+
+pub fn parse_offset_cursor(cursor: Option<String>) -> ZomeApiResult<usize> {
+    match cursor {
+        Some(cursor) => cursor.parse().map_err(|_| ZomeApiError::Internal("Invalid cursor".into())),
+        None => Ok(0),
+    }
+}
+
+/// Anchors are just strings so anyone can compute their address
+pub fn anchor_entry(name: &str) -> Entry {
+    Entry::App(
+        "anchor".into(),
+        name.into(),
+    )
+}
+
+fn proposal_bucket(timestamp: u32) -> u32 {
+    timestamp / PROPOSAL_BUCKET_SECONDS
+}
+
+fn proposal_anchor(bucket: u32) -> Entry {
+    anchor_entry(&format!("game_proposals:{}", bucket))
+}
+
+pub fn handle_create_proposal(message: String, created_at: u32) -> ZomeApiResult<Address> {
 
     // create the data as a struct
     let game_proposal_data = GameProposal { 
         agent: AGENT_ADDRESS.to_string().into(),
         message,
+        created_at,
     };
     
     // create an entry
@@ -56,13 +136,9 @@ pub fn handle_create_proposal(message: String) -> ZomeApiResult<Address> {
     // commit the entry. '?' means return immedietly on error
     let proposal_address = hdk::commit_entry(&entry)?;
     
-    // create an anchor entry and commit.
+    // create the anchor entry for this proposal's time bucket and commit.
     // The native type is string so we can skip the first step
-    let anchor_entry = Entry::App(
-        "anchor".into(),
-        "game_proposals".into(),
-    );
-    let anchor_address = hdk::commit_entry(&anchor_entry)?;
+    let anchor_address = hdk::commit_entry(&proposal_anchor(proposal_bucket(created_at)))?;
     
     // finally link them together
     hdk::link_entries(
@@ -72,44 +148,81 @@ pub fn handle_create_proposal(message: String) -> ZomeApiResult<Address> {
         "" // the tag which is not used in this example
     )?;
     
+    signal::emit(GameSignal::NewProposal{proposal: proposal_address.clone()});
+
     // return the proposal address
     Ok(proposal_address)
 }
 
-pub fn handle_get_proposals() -> ZomeApiResult<Vec<GetResponse<GameProposal>>> {
-    // define the anchor entry again and compute its hash
-    let anchor_address = Entry::App(
-        "anchor".into(),
-        "game_proposals".into()
-    ).address();
-    
-    Ok(
-        hdk::utils::get_links_and_load_type(
-            &anchor_address, 
-            LinkMatch::Exactly("has_proposal"), // the link type to match
-            LinkMatch::Any
-        )?.into_iter().map(|proposal: GameProposal| {
-            let address = Entry::App("game_proposal".into(), proposal.clone().into()).address();
-            GetResponse{entry: proposal, address}
-        }).collect()
-    )
+// proposal cursors are "<bucket>:<offset into bucket>"
+fn parse_proposal_cursor(cursor: &str) -> ZomeApiResult<(u32, usize)> {
+    let mut parts = cursor.splitn(2, ':');
+    match (parts.next().map(str::parse), parts.next().map(str::parse)) {
+        (Some(Ok(bucket)), Some(Ok(offset))) => Ok((bucket, offset)),
+        _ => Err(ZomeApiError::Internal("Invalid cursor".into())),
+    }
+}
+
+pub fn handle_get_proposals(timestamp: u32, cursor: Option<String>, limit: Option<usize>) -> ZomeApiResult<Page<GetResponse<GameProposal>>> {
+    let limit = limit.unwrap_or(DEFAULT_PAGE_SIZE);
+    let newest_bucket = proposal_bucket(timestamp);
+    let oldest_bucket = newest_bucket.saturating_sub(RECENT_BUCKETS - 1);
+    let (mut bucket, mut offset) = match cursor {
+        Some(cursor) => parse_proposal_cursor(&cursor)?,
+        None => (newest_bucket, 0),
+    };
+
+    // proposals from blocked agents are skipped while paging so a page is still filled when it can be
+    let blocked = block::handle_get_blocked_agents()?;
+    let mut items = Vec::new();
+    let mut buckets_read = 0;
+    // walk backwards in time through the bucket anchors
+    let next_cursor = loop {
+        if bucket < oldest_bucket {
+            break None
+        }
+        // once the page is full or enough buckets were read carry on from here next time
+        if buckets_read == BUCKETS_PER_PAGE || items.len() == limit {
+            break Some(format!("{}:0", bucket))
+        }
+        let (proposals, next_offset) = get_links_page(
+            &proposal_anchor(bucket).address(),
+            "has_proposal",
+            offset,
+            limit - items.len(),
+            |proposal: &GameProposal| !blocked.contains(&proposal.agent),
+        )?;
+        items.extend(proposals);
+        if let Some(next_offset) = next_offset {
+            break Some(format!("{}:{}", bucket, next_offset))
+        }
+        if bucket == 0 {
+            break None
+        }
+        buckets_read += 1;
+        bucket -= 1;
+        offset = 0;
+    };
+
+    Ok(Page{items, next_cursor})
 }
 
-pub fn handle_accept_proposal(proposal_addr: Address, created_at: u32) -> ZomeApiResult<Address> {
+pub fn handle_accept_proposal(proposal_addr: Address, created_at: u32, first_move: FirstMove) -> ZomeApiResult<Address> {
     // this will early return error if it doesn't exist
     let proposal: GameProposal = hdk::utils::get_as_type(proposal_addr.clone())?;
+    block::check_not_blocked(&proposal.agent)?;
 
     // create the new game
+    let me: Address = AGENT_ADDRESS.to_string().into();
     let game = Game {
-        player_1: AGENT_ADDRESS.to_string().into(),
-        player_2: proposal.agent,
+        player_1: me.clone(),
+        player_2: proposal.agent.clone(),
         created_at,
+        first_player: first_move.first_player(&me, &proposal.agent),
+        time_control: TimeControl::Unlimited,
+        inactivity_window: DEFAULT_INACTIVITY_WINDOW,
     };
-    let game_entry = Entry::App(
-        "game".into(),
-        game.into()
-    );
-    let game_addr = hdk::commit_entry(&game_entry)?;
+    let game_addr = game::commit_game(game)?;
 
     // link to the proposal
     hdk::link_entries(
@@ -118,17 +231,24 @@ pub fn handle_accept_proposal(proposal_addr: Address, created_at: u32) -> ZomeAp
         "from_proposal",
         ""
     )?;
+
+    // let this agent's client and, if they are online, the proposer know about the new game
+    signal::emit(GameSignal::ProposalAccepted{proposal: proposal_addr.clone(), game: game_addr.clone()});
+    notify::notify_proposal_accepted(proposal.agent, &proposal_addr, &game_addr);
     Ok(game_addr)
 }
 
-pub fn handle_check_responses(proposal_addr: Address) -> ZomeApiResult<Vec<GetResponse<Game>>> {
-    Ok(
-        hdk::utils::get_links_and_load_type(&proposal_addr, LinkMatch::Exactly("from_proposal".into()), LinkMatch::Any)?
-        .into_iter().map(|game: Game| {
-            let address = Entry::App("game".into(), game.clone().into()).address();
-            GetResponse{entry: game, address}
-        }).collect()
-    )
+pub fn handle_check_responses(proposal_addr: Address, cursor: Option<String>, limit: Option<usize>) -> ZomeApiResult<Page<GetResponse<Game>>> {
+    let offset = parse_offset_cursor(cursor)?;
+    let blocked = block::handle_get_blocked_agents()?;
+    let (items, next_offset) = get_links_page(
+        &proposal_addr,
+        "from_proposal",
+        offset,
+        limit.unwrap_or(DEFAULT_PAGE_SIZE),
+        |game: &Game| !blocked.contains(&game.player_1),
+    )?;
+    Ok(Page{items, next_cursor: next_offset.map(|offset| offset.to_string())})
 }
 
 pub fn handle_remove_proposal(proposal_addr: Address) -> ZomeApiResult<Address> {
@@ -199,6 +319,26 @@ pub fn anchor_def() -> ValidatingEntryType {
                 validation: | _validation_data: hdk::LinkValidationData| {
                     Ok(())
                 }
+            ),
+            to!(
+                "%agent_id",
+                link_type: "has_agent",
+                validation_package: || {
+                    hdk::ValidationPackageDefinition::Entry
+                },
+                validation: | _validation_data: hdk::LinkValidationData| {
+                    Ok(())
+                }
+            ),
+            to!(
+                "%agent_id",
+                link_type: "ranked_player",
+                validation_package: || {
+                    hdk::ValidationPackageDefinition::Entry
+                },
+                validation: | validation_data: hdk::LinkValidationData| {
+                    leaderboard::validate_ranked_player(LinkChange::from_validation_data(validation_data))
+                }
             )
         ]
     )
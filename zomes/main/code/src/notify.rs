@@ -0,0 +1,149 @@
+use std::convert::TryFrom;
+use hdk::{
+    AGENT_ADDRESS,
+    entry_definition::ValidatingEntryType,
+    error::ZomeApiResult,
+    holochain_persistence_api::{
+        cas::content::{AddressableContent, Address},
+    },
+    holochain_json_api::{
+        error::JsonError, json::JsonString,
+    },
+    holochain_core_types::{
+        dna::entry_types::Sharing,
+        entry::Entry,
+        time::Timeout,
+    }
+};
+
+use crate::game::{self, Game};
+use crate::game_move::Move;
+use crate::signal::{self, GameSignal};
+use crate::{GameState, Outcome};
+
+// don't hold up make_move for long when the opponent is offline, an online
+// opponent answers well within this and one who isn't will poll for the move anyway
+const SEND_TIMEOUT_MS: usize = 500;
+
+/// Sent directly to the opponent after a move so they don't have to wait for gossip.
+/// The move itself is included as it has usually not reached the opponent's DHT shard yet.
+#[derive(Serialize, Deserialize, Debug, DefaultJson, Clone, PartialEq)]
+pub struct MoveNotification {
+    pub game: Address,
+    pub move_address: Address,
+    pub author: Address,
+    pub game_move: Move,
+}
+
+/// Messages agents send to each other directly
+#[derive(Serialize, Deserialize, Debug, DefaultJson, Clone)]
+pub enum DirectMessage {
+    Move(MoveNotification),
+    ProposalAccepted{proposal: Address, game: Address},
+}
+
+/// Tell the opponent about a move. Returns false if they could not be reached,
+/// in which case they will find the move by polling the DHT as before.
+pub fn notify_opponent(game: &Game, game_address: &Address, move_address: &Address, game_move: &Move) -> bool {
+    let me: Address = AGENT_ADDRESS.to_string().into();
+    let opponent = if game.player_1 == me { game.player_2.clone() } else { game.player_1.clone() };
+    let notification = MoveNotification {
+        game: game_address.clone(),
+        move_address: move_address.clone(),
+        author: me,
+        game_move: game_move.clone(),
+    };
+    send(opponent, DirectMessage::Move(notification))
+}
+
+/// Tell the author of a proposal that it was accepted
+pub fn notify_proposal_accepted(proposer: Address, proposal: &Address, game: &Address) -> bool {
+    send(proposer, DirectMessage::ProposalAccepted{proposal: proposal.clone(), game: game.clone()})
+}
+
+fn send(to: Address, message: DirectMessage) -> bool {
+    let payload = JsonString::from(message).to_string();
+    hdk::send(to, payload, Timeout::new(SEND_TIMEOUT_MS)).is_ok()
+}
+
+/// Handle a message sent directly from another agent
+pub fn handle_receive(from: Address, payload: String) -> String {
+    match DirectMessage::try_from(JsonString::from_json(&payload)) {
+        Ok(DirectMessage::Move(notification)) => receive_move(from, notification),
+        Ok(DirectMessage::ProposalAccepted{proposal, game}) => receive_proposal_accepted(from, proposal, game),
+        Err(_) => "Unknown message".into(),
+    }
+}
+
+fn receive_proposal_accepted(from: Address, proposal: Address, game_address: Address) -> String {
+    match game::get_game(&game_address) {
+        Ok(ref game) if game.player_1 == from || game.player_2 == from => {
+            signal::emit(GameSignal::ProposalAccepted{proposal, game: game_address});
+            "Received".into()
+        },
+        _ => "Game not found".into(),
+    }
+}
+
+fn receive_move(from: Address, notification: MoveNotification) -> String {
+    if notification.author != from {
+        return "Notifications can only be sent about your own moves".into()
+    }
+
+    // the move is checked against its address rather than fetched as it may not have been gossiped yet
+    let game_move = notification.game_move.clone();
+    if Entry::App("move".into(), game_move.clone().into()).address() != notification.move_address {
+        return "The move does not match its address".into()
+    }
+    if game_move.author != from || game_move.game != notification.game {
+        return "Notifications can only be sent about your own moves".into()
+    }
+    signal::emit(GameSignal::OpponentMoved{
+        game: notification.game.clone(),
+        move_address: notification.move_address.clone(),
+    });
+    if let Ok(Some(outcome)) = outcome_after(&game_move) {
+        signal::emit(GameSignal::GameFinished{game: notification.game.clone(), outcome});
+    }
+    let entry = Entry::App(
+        "move_notification".into(),
+        notification.into(),
+    );
+    match hdk::commit_entry(&entry) {
+        Ok(_) => "Received".into(),
+        Err(err) => err.to_string(),
+    }
+}
+
+// the links to the move may not have reached us yet so add it to the published moves if needed
+fn outcome_after(game_move: &Move) -> ZomeApiResult<Option<Outcome>> {
+    let game = game::get_game(&game_move.game)?;
+    let mut moves = game::get_moves(&game_move.game)?;
+    if !moves.contains(game_move) {
+        moves.push(game_move.clone());
+    }
+    Ok(moves.iter().fold(GameState::initial(), |state, next_move| state.evolve(game.clone(), next_move)).outcome)
+}
+
+/// Every move notification this agent has received, oldest first
+pub fn handle_get_notifications() -> ZomeApiResult<Vec<MoveNotification>> {
+    hdk::query("move_notification".into(), 0, 0)?
+        .into_iter()
+        .map(|address| hdk::utils::get_as_type(address))
+        .collect()
+}
+
+pub fn move_notification_def() -> ValidatingEntryType {
+    entry!(
+        name: "move_notification",
+        description: "A private record of being told about an opponent's move",
+        sharing: Sharing::Private,
+        validation_package: || {
+            hdk::ValidationPackageDefinition::Entry
+        },
+        validation: | _validation_data: hdk::EntryValidationData<MoveNotification>| {
+            Ok(())
+        },
+        links: []
+    )
+}
@@ -0,0 +1,263 @@
+use hdk::{
+    AGENT_ADDRESS,
+    entry_definition::ValidatingEntryType,
+    error::{ZomeApiResult, ZomeApiError},
+    holochain_persistence_api::{
+        cas::content::{AddressableContent, Address},
+    },
+    holochain_json_api::{
+        error::JsonError, json::JsonString,
+    },
+    holochain_core_types::{
+        dna::entry_types::Sharing,
+        validation::EntryValidationData,
+        entry::Entry,
+        link::LinkMatch,
+    }
+};
+
+use rules::time::seconds_since_epoch;
+use rules::your_game::validation::TIMESTAMP_TOLERANCE;
+
+use crate::matchmaking::{anchor_entry, parse_offset_cursor, Page, DEFAULT_PAGE_SIZE};
+
+const DIRECTORY_ANCHOR: &str = "agent_directory";
+const MIN_NICKNAME_LENGTH: usize = 3;
+const MAX_NICKNAME_LENGTH: usize = 20;
+const MAX_BIO_LENGTH: usize = 280;
+
+/// How an agent presents themselves to other players
+#[derive(Serialize, Deserialize, Debug, DefaultJson, Clone, PartialEq)]
+pub struct Profile {
+    pub agent: Address,
+    pub nickname: String,
+    pub bio: String,
+    pub preferred_variant: Option<String>,
+    // when the agent first took this nickname, which settles who keeps it if two agents claim it at once
+    pub claimed_at: u32,
+}
+
+// nicknames are unique regardless of case so they all share one anchor
+fn nickname_anchor(nickname: &str) -> Entry {
+    anchor_entry(&format!("nickname:{}", nickname.to_lowercase()))
+}
+
+/// Create or replace the profile of this agent.
+/// A nickname is checked against the DHT when it is claimed, but two agents claiming the same
+/// nickname at once can both pass that check before either link has gossiped. The DHT then holds
+/// both claims, so readers settle it with `holds_nickname` and the earlier claim keeps the nickname.
+pub fn handle_set_profile(nickname: String, bio: String, preferred_variant: Option<String>, timestamp: u32) -> ZomeApiResult<Address> {
+    let me: Address = AGENT_ADDRESS.to_string().into();
+    let previous = get_profile_link(&me)?;
+    // changing the bio or the case of the nickname keeps the original claim
+    let claimed_at = match previous {
+        Some((_, ref previous_profile)) if previous_profile.nickname.to_lowercase() == nickname.to_lowercase() => previous_profile.claimed_at,
+        _ => timestamp,
+    };
+    let profile = Profile {
+        agent: me.clone(),
+        nickname,
+        bio,
+        preferred_variant,
+        claimed_at,
+    };
+    // validation would reject these too but this gives a clearer error
+    check_profile(&profile).map_err(ZomeApiError::Internal)?;
+    if nickname_taken(&profile)? {
+        return Err(ZomeApiError::Internal(format!("The nickname {} is already taken", profile.nickname)))
+    }
+
+    if let Some((_, ref previous_profile)) = previous {
+        if *previous_profile == profile {
+            return Ok(Entry::App("profile".into(), profile.into()).address())
+        }
+    }
+
+    let entry = Entry::App(
+        "profile".into(),
+        profile.clone().into(),
+    );
+    let profile_address = hdk::commit_entry(&entry)?;
+    let anchor_address = hdk::commit_entry(&nickname_anchor(&profile.nickname))?;
+    hdk::link_entries(&anchor_address, &profile_address, "has_profile", "")?;
+    hdk::link_entries(&me, &profile_address, "agent->profile", "")?;
+
+    match previous {
+        // free up the old nickname and only leave the new profile linked from the agent
+        Some((previous_address, previous_profile)) => {
+            hdk::remove_link(&nickname_anchor(&previous_profile.nickname).address(), &previous_address, "has_profile", "")?;
+            hdk::remove_link(&me, &previous_address, "agent->profile", "")?;
+        },
+        None => {
+            let directory_address = hdk::commit_entry(&anchor_entry(DIRECTORY_ANCHOR))?;
+            hdk::link_entries(&directory_address, &me, "has_agent", "")?;
+        },
+    }
+    Ok(profile_address)
+}
+
+/// The profile of an agent, or None if they haven't set one or lost their nickname to an earlier claim
+pub fn handle_get_profile(agent: Address) -> ZomeApiResult<Option<Profile>> {
+    match get_profile_link(&agent)? {
+        Some((_, profile)) => Ok(if holds_nickname(&profile)? { Some(profile) } else { None }),
+        None => Ok(None),
+    }
+}
+
+/// The profiles of every agent who has set one, ordered by nickname
+pub fn handle_get_agent_directory(limit: Option<usize>, cursor: Option<String>) -> ZomeApiResult<Page<Profile>> {
+    let offset = parse_offset_cursor(cursor)?;
+    let limit = limit.unwrap_or(DEFAULT_PAGE_SIZE);
+
+    let mut profiles: Vec<Profile> = Vec::new();
+    for agent in hdk::get_links(&anchor_entry(DIRECTORY_ANCHOR).address(), LinkMatch::Exactly("has_agent"), LinkMatch::Any)?.addresses() {
+        if profiles.iter().any(|profile| profile.agent == agent) {
+            continue
+        }
+        if let Some(profile) = handle_get_profile(agent)? {
+            profiles.push(profile);
+        }
+    }
+    profiles.sort_by(|a, b| a.nickname.to_lowercase().cmp(&b.nickname.to_lowercase()));
+
+    let next_cursor = if offset + limit < profiles.len() {
+        Some((offset + limit).to_string())
+    } else {
+        None
+    };
+    let items = profiles.into_iter().skip(offset).take(limit).collect();
+    Ok(Page{items, next_cursor})
+}
+
+fn get_profile_link(agent: &Address) -> ZomeApiResult<Option<(Address, Profile)>> {
+    match hdk::get_links(agent, LinkMatch::Exactly("agent->profile"), LinkMatch::Any)?.addresses().into_iter().next() {
+        Some(address) => {
+            let profile: Profile = hdk::utils::get_as_type(address.clone())?;
+            Ok(Some((address, profile)))
+        },
+        None => Ok(None),
+    }
+}
+
+fn nickname_holders(nickname: &str) -> ZomeApiResult<Vec<Profile>> {
+    hdk::utils::get_links_and_load_type(
+        &nickname_anchor(nickname).address(),
+        LinkMatch::Exactly("has_profile"),
+        LinkMatch::Any
+    )
+}
+
+// another agent already has a profile linked from the nickname anchor
+fn nickname_taken(profile: &Profile) -> ZomeApiResult<bool> {
+    Ok(nickname_holders(&profile.nickname)?.iter().any(|holder| holder.agent != profile.agent))
+}
+
+// Of every agent linked from the nickname anchor the one with the earliest claim keeps it,
+// with the lower agent address winning a tie so every reader picks the same one
+fn holds_nickname(profile: &Profile) -> ZomeApiResult<bool> {
+    let claim = (profile.claimed_at, profile.agent.to_string());
+    Ok(nickname_holders(&profile.nickname)?.into_iter()
+        .filter(|holder| holder.agent != profile.agent)
+        .all(|holder| (holder.claimed_at, holder.agent.to_string()) > claim))
+}
+
+// the parts of a link to a profile which validation needs
+struct ProfileLink {
+    base: Address,
+    profile: Profile,
+}
+
+impl ProfileLink {
+    /// The link being added or removed and whether it is being added.
+    /// Only the agent whose profile it is can link to or unlink from it.
+    fn load(validation_data: hdk::LinkValidationData) -> Result<(ProfileLink, bool), String> {
+        let (link, validation_data, adding) = match validation_data {
+            hdk::LinkValidationData::LinkAdd{link, validation_data} => (link, validation_data, true),
+            hdk::LinkValidationData::LinkRemove{link, validation_data} => (link, validation_data, false),
+        };
+        let profile: Profile = hdk::utils::get_as_type(link.link().target().clone())
+            .map_err(|_| "Could not load the linked profile during validation".to_string())?;
+        if !validation_data.sources().contains(&profile.agent) {
+            return Err("Only the agent a profile belongs to can link it".into())
+        }
+        Ok((ProfileLink{base: link.link().base().clone(), profile}, adding))
+    }
+}
+
+fn check_profile(profile: &Profile) -> Result<(), String> {
+    let length = profile.nickname.chars().count();
+    if length < MIN_NICKNAME_LENGTH || length > MAX_NICKNAME_LENGTH {
+        return Err(format!("A nickname must be between {} and {} characters", MIN_NICKNAME_LENGTH, MAX_NICKNAME_LENGTH))
+    }
+    if !profile.nickname.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '-') {
+        return Err("A nickname can only contain letters, numbers, _ and -".into())
+    }
+    if profile.bio.chars().count() > MAX_BIO_LENGTH {
+        return Err(format!("A bio can't be longer than {} characters", MAX_BIO_LENGTH))
+    }
+    Ok(())
+}
+
+pub fn profile_def() -> ValidatingEntryType {
+    entry!(
+        name: "profile",
+        description: "The nickname and details an agent shows to other players",
+        sharing: Sharing::Public,
+        validation_package: || {
+            hdk::ValidationPackageDefinition::Entry
+        },
+        validation: | validation_data: hdk::EntryValidationData<Profile>| {
+            match validation_data {
+                EntryValidationData::Create{ entry, validation_data } => {
+                    let profile = Profile::from(entry);
+                    if !validation_data.sources().contains(&profile.agent) {
+                        return Err("Cannot create a profile for another agent".into())
+                    }
+                    // a claim can be carried over from an earlier profile but can't be made in advance
+                    let committed_at = seconds_since_epoch(&validation_data.package.chain_header.timestamp().to_string())?;
+                    if profile.claimed_at > committed_at.saturating_add(TIMESTAMP_TOLERANCE) {
+                        return Err("A nickname can't be claimed later than the profile was made".into())
+                    }
+                    // uniqueness depends on what else is on the DHT so it is checked on the has_profile link instead
+                    check_profile(&profile)
+                },
+                _ => {
+                    Err("Cannot modify or delete a profile, set a new one instead".into())
+                }
+            }
+        },
+        links: [
+            from!(
+                "anchor",
+                link_type: "has_profile",
+                validation_package: || { hdk::ValidationPackageDefinition::Entry },
+                validation: | validation_data: hdk::LinkValidationData| {
+                    let (link, adding) = ProfileLink::load(validation_data)?;
+                    if link.base != nickname_anchor(&link.profile.nickname).address() {
+                        return Err("A profile can only be linked from the anchor of its own nickname".into())
+                    }
+                    if !adding {
+                        return Ok(())
+                    }
+                    match nickname_taken(&link.profile) {
+                        Ok(false) => Ok(()),
+                        Ok(true) => Err(format!("The nickname {} is already taken", link.profile.nickname)),
+                        Err(_) => Err("Could not look up the nickname during validation".into()),
+                    }
+                }
+            ),
+            from!(
+                "%agent_id",
+                link_type: "agent->profile",
+                validation_package: || { hdk::ValidationPackageDefinition::Entry },
+                validation: | validation_data: hdk::LinkValidationData| {
+                    let (link, _) = ProfileLink::load(validation_data)?;
+                    if link.base != link.profile.agent {
+                        return Err("A profile can only be linked from its own agent".into())
+                    }
+                    Ok(())
+                }
+            )
+        ]
+    )
+}
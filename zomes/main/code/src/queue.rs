@@ -0,0 +1,243 @@
+use hdk::{
+    AGENT_ADDRESS,
+    entry_definition::ValidatingEntryType,
+    error::{ZomeApiResult, ZomeApiError},
+    holochain_persistence_api::{
+        cas::content::{AddressableContent, Address},
+    },
+    holochain_json_api::{
+        error::JsonError, json::JsonString,
+    },
+    holochain_core_types::{
+        dna::entry_types::Sharing,
+        validation::EntryValidationData,
+        entry::Entry,
+        link::LinkMatch,
+    }
+};
+
+use rules::time::seconds_since_epoch;
+use rules::your_game::validation::TIMESTAMP_TOLERANCE;
+
+use crate::block;
+use crate::game::{self, Game, TimeControl, DEFAULT_INACTIVITY_WINDOW};
+use crate::links::{LinkChange, load_linked};
+use crate::matchmaking::{anchor_entry, GetResponse};
+
+const QUEUE_ANCHOR: &str = "play_queue";
+/// Agents who joined longer ago than this are assumed to have gone away and are not matched
+const QUEUE_WINDOW: u32 = 30 * 60;
+
+/// An agent waiting in the "play now" queue
+#[derive(Serialize, Deserialize, Debug, DefaultJson, Clone)]
+pub struct QueueEntry {
+    pub agent: Address,
+    pub joined_at: u32,
+}
+
+pub fn handle_join_queue(joined_at: u32) -> ZomeApiResult<Address> {
+    let queue_entry = QueueEntry {
+        agent: AGENT_ADDRESS.to_string().into(),
+        joined_at,
+    };
+    let entry = Entry::App(
+        "queue_entry".into(),
+        queue_entry.into(),
+    );
+    let entry_address = hdk::commit_entry(&entry)?;
+    let anchor_address = hdk::commit_entry(&anchor_entry(QUEUE_ANCHOR))?;
+    hdk::link_entries(
+        &anchor_address,
+        &entry_address,
+        "queued",
+        ""
+    )?;
+    Ok(entry_address)
+}
+
+pub fn handle_leave_queue() -> ZomeApiResult<()> {
+    let anchor_address = anchor_entry(QUEUE_ANCHOR).address();
+    for queued in get_queue()? {
+        if is_me(&queued.entry.agent) {
+            hdk::remove_link(&anchor_address, &queued.address, "queued", "")?;
+        }
+    }
+    Ok(())
+}
+
+/// Look for a game for this agent. Returns the game address once matched.
+///
+/// Waiting agents who joined within QUEUE_WINDOW of `timestamp` are sorted oldest
+/// first and paired off two at a time. Because
+/// the pairing and the resulting game entry only depend on the queue entries,
+/// both agents of a pair (or anyone racing to match them) commit the very same
+/// game and so end up with the same address.
+///
+/// Blocks are private so they can't change the pairing everyone agrees on. Instead an
+/// agent paired with someone they blocked leaves the queue and is told to join again.
+pub fn handle_find_match(timestamp: u32) -> ZomeApiResult<Option<Address>> {
+    let queue = get_queue()?;
+
+    // someone may already have matched us
+    let mut waiting = Vec::new();
+    for queued in queue {
+        let games = hdk::get_links(&queued.address, LinkMatch::Exactly("queue->game"), LinkMatch::Any)?.addresses();
+        match games.into_iter().next() {
+            Some(game_address) => {
+                if is_me(&queued.entry.agent) {
+                    let game: Game = hdk::utils::get_as_type(game_address.clone())?;
+                    let opponent = if is_me(&game.player_1) { game.player_2 } else { game.player_1 };
+                    handle_leave_queue()?;
+                    if block::is_blocked(&opponent)? {
+                        return Err(blocked_match())
+                    }
+                    return Ok(Some(game_address))
+                }
+            },
+            None => if queued.entry.joined_at.saturating_add(QUEUE_WINDOW) >= timestamp {
+                waiting.push(queued)
+            },
+        }
+    }
+
+    let pair = pair_waiting(waiting).into_iter().find(|(first, second)| {
+        is_me(&first.entry.agent) || is_me(&second.entry.agent)
+    });
+
+    match pair {
+        Some((first, second)) => {
+            let opponent = if is_me(&first.entry.agent) { &second.entry.agent } else { &first.entry.agent };
+            if block::is_blocked(opponent)? {
+                handle_leave_queue()?;
+                return Err(blocked_match())
+            }
+            let game = Game {
+                player_1: first.entry.agent.clone(),
+                player_2: second.entry.agent.clone(),
+                created_at: second.entry.joined_at,
+                // as in any game nobody chose for, player_2 moves first
+                first_player: Some(second.entry.agent.clone()),
+                time_control: TimeControl::Unlimited,
+                inactivity_window: DEFAULT_INACTIVITY_WINDOW,
+            };
+            let game_address = game::commit_game(game)?;
+            hdk::link_entries(&first.address, &game_address, "queue->game", "")?;
+            hdk::link_entries(&second.address, &game_address, "queue->game", "")?;
+            handle_leave_queue()?;
+            Ok(Some(game_address))
+        },
+        None => Ok(None),
+    }
+}
+
+fn get_queue() -> ZomeApiResult<Vec<GetResponse<QueueEntry>>> {
+    let anchor_address = anchor_entry(QUEUE_ANCHOR).address();
+    Ok(
+        hdk::utils::get_links_and_load_type(
+            &anchor_address,
+            LinkMatch::Exactly("queued"),
+            LinkMatch::Any
+        )?.into_iter().map(|queue_entry: QueueEntry| {
+            let address = Entry::App("queue_entry".into(), queue_entry.clone().into()).address();
+            GetResponse{entry: queue_entry, address}
+        }).collect()
+    )
+}
+
+// Keep only the oldest entry of each agent, order oldest first (ties broken by address)
+// and pair neighbours off.
+fn pair_waiting(mut waiting: Vec<GetResponse<QueueEntry>>) -> Vec<(GetResponse<QueueEntry>, GetResponse<QueueEntry>)> {
+    waiting.sort_by(|a, b| {
+        (a.entry.joined_at, a.address.to_string()).cmp(&(b.entry.joined_at, b.address.to_string()))
+    });
+    let mut agents: Vec<Address> = Vec::new();
+    waiting.retain(|queued| {
+        if agents.contains(&queued.entry.agent) {
+            false
+        } else {
+            agents.push(queued.entry.agent.clone());
+            true
+        }
+    });
+
+    let mut pairs = Vec::new();
+    let mut waiting = waiting.into_iter();
+    while let (Some(first), Some(second)) = (waiting.next(), waiting.next()) {
+        pairs.push((first, second));
+    }
+    pairs
+}
+
+fn blocked_match() -> ZomeApiError {
+    ZomeApiError::Internal("You were matched with an agent you have blocked, join the queue again to find someone else".into())
+}
+
+fn is_me(agent: &Address) -> bool {
+    agent.to_string() == AGENT_ADDRESS.to_string()
+}
+
+pub fn queue_entry_def() -> ValidatingEntryType {
+    entry!(
+        name: "queue_entry",
+        description: "Represents an agent waiting to be matched with any other agent",
+        sharing: Sharing::Public,
+        validation_package: || {
+            hdk::ValidationPackageDefinition::Entry
+        },
+        validation: | validation_data: hdk::EntryValidationData<QueueEntry>| {
+            match validation_data {
+                EntryValidationData::Create{ entry, validation_data } => {
+                    let queue_entry = QueueEntry::from(entry);
+                    if !validation_data.sources().contains(&queue_entry.agent) {
+                        return Err("Cannot queue up another agent".into())
+                    }
+                    // the join time decides who is matched first so it must be when the entry was committed
+                    let committed_at = seconds_since_epoch(&validation_data.package.chain_header.timestamp().to_string())?;
+                    if queue_entry.joined_at.saturating_add(TIMESTAMP_TOLERANCE) < committed_at
+                        || queue_entry.joined_at > committed_at.saturating_add(TIMESTAMP_TOLERANCE) {
+                        return Err("A queue entry must be timestamped with the time it was made".into())
+                    }
+                    Ok(())
+                },
+                _ => {
+                    Err("Cannot modify or delete a queue entry".into())
+                }
+            }
+        },
+        links: [
+            from!(
+                "anchor",
+                link_type: "queued",
+                validation_package: || { hdk::ValidationPackageDefinition::Entry },
+                validation: | validation_data: hdk::LinkValidationData| {
+                    let link = LinkChange::from_validation_data(validation_data);
+                    let queue_entry: QueueEntry = load_linked(&link.target, "queue entry")?;
+                    if link.base != anchor_entry(QUEUE_ANCHOR).address() {
+                        return Err("Queue entries can only be linked from the queue anchor".into())
+                    }
+                    if !link.authored_by_one_of(&[&queue_entry.agent]) {
+                        return Err("Only the queued agent can add or remove them from the queue".into())
+                    }
+                    Ok(())
+                }
+            ),
+            to!(
+                "game",
+                link_type: "queue->game",
+                validation_package: || { hdk::ValidationPackageDefinition::Entry },
+                validation: | validation_data: hdk::LinkValidationData| {
+                    let link = LinkChange::from_validation_data(validation_data);
+                    let queue_entry: QueueEntry = load_linked(&link.base, "queue entry")?;
+                    let game: Game = load_linked(&link.target, "game")?;
+                    if game.player_1 != queue_entry.agent && game.player_2 != queue_entry.agent {
+                        return Err("A queued agent can only be matched into a game they play in".into())
+                    }
+                    if !link.authored_by_one_of(&[&game.player_1, &game.player_2]) {
+                        return Err("Only the players of the game can record the match".into())
+                    }
+                    Ok(())
+                }
+            )
+        ]
+    )
+}
@@ -0,0 +1,245 @@
+use hdk::{
+    entry_definition::ValidatingEntryType,
+    error::ZomeApiResult,
+    holochain_persistence_api::{
+        cas::content::{AddressableContent, Address},
+    },
+    holochain_json_api::{
+        error::JsonError, json::JsonString,
+    },
+    holochain_core_types::{
+        dna::entry_types::Sharing,
+        validation::EntryValidationData,
+        entry::Entry,
+        link::LinkMatch,
+    }
+};
+
+use crate::game::{Game, get_game_local_chain, get_state_local_chain};
+use crate::leaderboard;
+use crate::links::LinkChange;
+use crate::matchmaking::anchor_entry;
+use crate::{GameState, Outcome};
+use rules::rating::{elo, INITIAL_RATING};
+
+const RESULTS_ANCHOR: &str = "game_results";
+
+/// The result of a finished game. Its content is fully determined by the moves
+/// of the game so either player may commit it and it will have the same address.
+#[derive(Clone, Debug, Serialize, Deserialize, DefaultJson, PartialEq)]
+pub struct GameResult {
+    pub game: Address,
+    pub player_1: Address,
+    pub player_2: Address,
+    pub outcome: Outcome,
+    pub finished_at: u32,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, DefaultJson)]
+pub struct RatingChange {
+    pub game: Address,
+    pub opponent: Address,
+    // 1 for a win, 0.5 for a draw and 0 for a loss
+    pub score: f64,
+    pub rating: f64,
+    pub finished_at: u32,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, DefaultJson)]
+pub struct Rating {
+    pub agent: Address,
+    pub rating: f64,
+    pub history: Vec<RatingChange>,
+}
+
+impl GameResult {
+    pub fn from_state(game_address: &Address, game: &Game, state: &GameState) -> Option<GameResult> {
+        state.outcome.clone().map(|outcome| GameResult {
+            game: game_address.clone(),
+            player_1: game.player_1.clone(),
+            player_2: game.player_2.clone(),
+            outcome,
+            finished_at: state.moves.last().map(|last_move| last_move.timestamp).unwrap_or(game.created_at),
+        })
+    }
+
+    /// How player 1 scored in this game, None if it should not count towards ratings
+    pub fn player_1_score(&self) -> Option<f64> {
+        match self.outcome {
+            Outcome::Draw => Some(0.5),
+            Outcome::Aborted => None,
+            _ => self.outcome.winner().map(|winner| {
+                if winner == self.player_1 { 1.0 } else { 0.0 }
+            }),
+        }
+    }
+}
+
+/// Commit the result of a finished game and index it
+pub fn record_result(game_address: &Address, game: &Game, state: &GameState) -> ZomeApiResult<Option<Address>> {
+    match GameResult::from_state(game_address, game, state) {
+        Some(result) => Ok(Some(commit_result(result)?)),
+        None => Ok(None),
+    }
+}
+
+/// Countersign a result, usually the one the opponent recorded. Validation rejects it
+/// unless it matches the moves of the game on this agent's chain.
+pub fn handle_record_game_result(result: GameResult) -> ZomeApiResult<Address> {
+    commit_result(result)
+}
+
+fn commit_result(result: GameResult) -> ZomeApiResult<Address> {
+    let result_entry = Entry::App(
+        "game_result".into(),
+        result.clone().into(),
+    );
+    let result_address = hdk::commit_entry(&result_entry)?;
+    let anchor_address = hdk::commit_entry(&anchor_entry(RESULTS_ANCHOR))?;
+    hdk::link_entries(&anchor_address, &result_address, "has_result", "")?;
+    hdk::link_entries(&result.game, &result_address, "game->result", "")?;
+    leaderboard::index_result(&result, &result_address)?;
+    Ok(result_address)
+}
+
+pub fn get_game_result(game_address: &Address) -> ZomeApiResult<Option<GameResult>> {
+    Ok(
+        hdk::utils::get_links_and_load_type(game_address, LinkMatch::Exactly("game->result"), LinkMatch::Any)?
+            .into_iter()
+            .next()
+    )
+}
+
+/// Every recorded result, oldest first and with the copies committed by both players merged
+pub fn get_results() -> ZomeApiResult<Vec<GameResult>> {
+    let mut results: Vec<GameResult> = hdk::utils::get_links_and_load_type(
+        &anchor_entry(RESULTS_ANCHOR).address(),
+        LinkMatch::Exactly("has_result"),
+        LinkMatch::Any
+    )?;
+    results.sort_by(|a, b| {
+        (a.finished_at, a.game.to_string()).cmp(&(b.finished_at, b.game.to_string()))
+    });
+    results.dedup();
+    Ok(results)
+}
+
+/// Replay every recorded result in order and report the Elo rating of the given agent
+pub fn handle_get_rating(agent: Address) -> ZomeApiResult<Rating> {
+    let mut ratings: Vec<(Address, f64)> = Vec::new();
+    let mut history = Vec::new();
+    for result in get_results()? {
+        let score = match result.player_1_score() {
+            Some(score) => score,
+            None => continue,
+        };
+        let rating_1 = current_rating(&ratings, &result.player_1);
+        let rating_2 = current_rating(&ratings, &result.player_2);
+        let new_rating_1 = elo(rating_1, rating_2, score);
+        let new_rating_2 = elo(rating_2, rating_1, 1.0 - score);
+        set_rating(&mut ratings, &result.player_1, new_rating_1);
+        set_rating(&mut ratings, &result.player_2, new_rating_2);
+
+        if result.player_1 == agent {
+            history.push(RatingChange {
+                game: result.game.clone(),
+                opponent: result.player_2.clone(),
+                score,
+                rating: new_rating_1,
+                finished_at: result.finished_at,
+            });
+        } else if result.player_2 == agent {
+            history.push(RatingChange {
+                game: result.game.clone(),
+                opponent: result.player_1.clone(),
+                score: 1.0 - score,
+                rating: new_rating_2,
+                finished_at: result.finished_at,
+            });
+        }
+    }
+
+    Ok(Rating {
+        rating: current_rating(&ratings, &agent),
+        agent,
+        history,
+    })
+}
+
+fn current_rating(ratings: &Vec<(Address, f64)>, agent: &Address) -> f64 {
+    ratings.iter()
+        .find(|(rated_agent, _)| rated_agent == agent)
+        .map(|(_, rating)| *rating)
+        .unwrap_or(INITIAL_RATING)
+}
+
+fn set_rating(ratings: &mut Vec<(Address, f64)>, agent: &Address, rating: f64) {
+    ratings.retain(|(rated_agent, _)| rated_agent != agent);
+    ratings.push((agent.clone(), rating));
+}
+
+pub fn game_result_def() -> ValidatingEntryType {
+    entry!(
+        name: "game_result",
+        description: "The outcome of a finished game, checked against the moves that produced it",
+        sharing: Sharing::Public,
+        validation_package: || {
+            hdk::ValidationPackageDefinition::ChainFull
+        },
+
+        validation: | validation_data: hdk::EntryValidationData<GameResult>| {
+            match validation_data {
+                EntryValidationData::Create{entry, validation_data} => {
+                    let result = GameResult::from(entry);
+                    if !validation_data.sources().contains(&result.player_1) && !validation_data.sources().contains(&result.player_2) {
+                        return Err("Only a player of a game can record its result".into())
+                    }
+
+                    // the author must have played the game so it is on their chain
+                    let local_chain = validation_data.package.source_chain_entries
+                        .ok_or("Could not retrieve source chain")?;
+                    let game = get_game_local_chain(local_chain.clone(), &result.game)
+                        .map_err(|_| "Could not load game during validation")?;
+                    let state = get_state_local_chain(local_chain, &result.game)
+                        .map_err(|_| "Could not load state during validation")?;
+
+                    match GameResult::from_state(&result.game, &game, &state) {
+                        Some(ref expected) if *expected == result => Ok(()),
+                        Some(_) => Err("Result does not match the moves of the game".into()),
+                        None => Err("Game is not finished".into()),
+                    }
+                },
+                _ => {
+                    Err("Cannot modify or delete a game result".into())
+                }
+            }
+        },
+
+        links: [
+            from!(
+                "anchor",
+                link_type: "has_result",
+                validation_package: || { hdk::ValidationPackageDefinition::Entry },
+                validation: | _validation_data: hdk::LinkValidationData| {
+                    Ok(())
+                }
+            ),
+            from!(
+                "game",
+                link_type: "game->result",
+                validation_package: || { hdk::ValidationPackageDefinition::Entry },
+                validation: | _validation_data: hdk::LinkValidationData| {
+                    Ok(())
+                }
+            ),
+            from!(
+                "%agent_id",
+                link_type: "ranked_result",
+                validation_package: || { hdk::ValidationPackageDefinition::Entry },
+                validation: | validation_data: hdk::LinkValidationData| {
+                    leaderboard::validate_ranked_result(LinkChange::from_validation_data(validation_data))
+                }
+            )
+        ]
+    )
+}
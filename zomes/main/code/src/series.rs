@@ -0,0 +1,238 @@
+use hdk::{
+    AGENT_ADDRESS,
+    entry_definition::ValidatingEntryType,
+    error::{ZomeApiResult, ZomeApiError},
+    holochain_persistence_api::{
+        cas::content::{AddressableContent, Address},
+    },
+    holochain_json_api::{
+        error::JsonError, json::JsonString,
+    },
+    holochain_core_types::{
+        dna::entry_types::Sharing,
+        validation::EntryValidationData,
+        entry::Entry,
+        link::LinkMatch,
+    }
+};
+
+use crate::game::{self, Game, TimeControl, DEFAULT_INACTIVITY_WINDOW};
+use crate::links::{LinkChange, load_linked};
+
+/// A best-of-N match between two agents, made up of several games
+#[derive(Clone, Debug, Serialize, Deserialize, DefaultJson)]
+pub struct Series {
+    pub player_1: Address,
+    pub player_2: Address,
+    pub best_of: u32,
+    pub created_at: u32,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, DefaultJson)]
+pub struct SeriesState {
+    pub games: Vec<Address>,
+    pub p1_wins: u32,
+    pub p2_wins: u32,
+    pub finished: bool,
+    pub winner: Option<Address>,
+}
+
+impl Series {
+    fn wins_needed(&self) -> u32 {
+        self.best_of / 2 + 1
+    }
+
+    // whether a game is between the two players of this series, in either order
+    fn is_played_in(&self, game: &Game) -> bool {
+        (game.player_1 == self.player_1 && game.player_2 == self.player_2)
+            || (game.player_1 == self.player_2 && game.player_2 == self.player_1)
+    }
+}
+
+pub fn handle_create_series(opponent: Address, best_of: u32, time_control: TimeControl, created_at: u32) -> ZomeApiResult<Address> {
+    let series = Series {
+        player_1: AGENT_ADDRESS.to_string().into(),
+        player_2: opponent.clone(),
+        best_of,
+        created_at,
+    };
+    let series_entry = Entry::App(
+        "series".into(),
+        series.into(),
+    );
+    let series_addr = hdk::commit_entry(&series_entry)?;
+
+    let first_game = Game {
+        player_1: AGENT_ADDRESS.to_string().into(),
+        player_2: opponent.clone(),
+        created_at,
+        first_player: Some(opponent),
+        time_control,
+        inactivity_window: DEFAULT_INACTIVITY_WINDOW,
+    };
+    let game_addr = game::commit_game(first_game)?;
+    add_to_series(&series_addr, &game_addr)?;
+    Ok(series_addr)
+}
+
+/// Start a new game against the same opponent once a game has finished.
+/// Whoever moved second last time moves first in the rematch.
+pub fn handle_rematch(game_addr: Address) -> ZomeApiResult<Address> {
+    // if the opponent already asked for a rematch play that one
+    if let Some(existing) = hdk::get_links(&game_addr, LinkMatch::Exactly("rematch"), LinkMatch::Any)?.addresses().into_iter().next() {
+        return Ok(existing)
+    }
+
+    let previous = game::get_game(&game_addr)?;
+    let agent_address: Address = AGENT_ADDRESS.to_string().into();
+    if agent_address != previous.player_1 && agent_address != previous.player_2 {
+        return Err(ZomeApiError::Internal("Only the players of a game can ask for a rematch".into()))
+    }
+    let state = game::get_state(&game_addr)?;
+    if !state.is_finished() {
+        return Err(ZomeApiError::Internal("The game has not finished yet".into()))
+    }
+
+    let previous_first = state.token_moves().first()
+        .map(|first_move| first_move.author.clone())
+        .or(previous.first_player.clone())
+        .unwrap_or(previous.player_1.clone());
+    let first_player = if previous_first == previous.player_1 {
+        previous.player_2.clone()
+    } else {
+        previous.player_1.clone()
+    };
+
+    // the rematch starts when the last game ended so both players asking at once
+    // commit the very same entry rather than two different games
+    let created_at = state.moves.last()
+        .map(|last_move| last_move.timestamp)
+        .unwrap_or(previous.created_at);
+    let rematch = Game {
+        player_1: previous.player_1.clone(),
+        player_2: previous.player_2.clone(),
+        created_at,
+        first_player: Some(first_player),
+        time_control: previous.time_control.clone(),
+        inactivity_window: previous.inactivity_window,
+    };
+    let rematch_addr = game::commit_game(rematch)?;
+    hdk::link_entries(&game_addr, &rematch_addr, "rematch", "")?;
+
+    // keep playing the series unless it has already been decided
+    if let Some(series_addr) = hdk::get_links(&game_addr, LinkMatch::Exactly("in_series"), LinkMatch::Any)?.addresses().into_iter().next() {
+        if !handle_get_series_state(series_addr.clone())?.finished {
+            add_to_series(&series_addr, &rematch_addr)?;
+        }
+    }
+    Ok(rematch_addr)
+}
+
+/// The running score of a series
+pub fn handle_get_series_state(series_addr: Address) -> ZomeApiResult<SeriesState> {
+    let series: Series = hdk::utils::get_as_type(series_addr.clone())?;
+
+    // validation only links games between the series players, skip any others that slip through
+    let mut games: Vec<Address> = Vec::new();
+    for game_addr in hdk::get_links(&series_addr, LinkMatch::Exactly("series->game"), LinkMatch::Any)?.addresses() {
+        if !games.contains(&game_addr) && series.is_played_in(&game::get_game(&game_addr)?) {
+            games.push(game_addr);
+        }
+    }
+
+    let mut p1_wins = 0;
+    let mut p2_wins = 0;
+    for game_addr in &games {
+        match game::get_state(game_addr)?.winner() {
+            Some(ref winner) if *winner == series.player_1 => p1_wins += 1,
+            Some(ref winner) if *winner == series.player_2 => p2_wins += 1,
+            _ => (),
+        }
+    }
+
+    let winner = if p1_wins >= series.wins_needed() {
+        Some(series.player_1.clone())
+    } else if p2_wins >= series.wins_needed() {
+        Some(series.player_2.clone())
+    } else {
+        None
+    };
+
+    Ok(SeriesState {
+        games,
+        p1_wins,
+        p2_wins,
+        finished: winner.is_some(),
+        winner,
+    })
+}
+
+fn add_to_series(series_addr: &Address, game_addr: &Address) -> ZomeApiResult<()> {
+    hdk::link_entries(series_addr, game_addr, "series->game", "")?;
+    hdk::link_entries(game_addr, series_addr, "in_series", "")?;
+    Ok(())
+}
+
+// Only the players of a series can add a game to it, and it must be a game between them
+fn check_series_link(link: &LinkChange, series_addr: &Address, game_addr: &Address) -> Result<(), String> {
+    let series: Series = load_linked(series_addr, "series")?;
+    let game: Game = load_linked(game_addr, "game")?;
+    if !link.authored_by_one_of(&[&series.player_1, &series.player_2]) {
+        return Err("Only the players of a series can add games to it".into())
+    }
+    if !series.is_played_in(&game) {
+        return Err("A series can only include games between its players".into())
+    }
+    Ok(())
+}
+
+pub fn series_def() -> ValidatingEntryType {
+    entry!(
+        name: "series",
+        description: "Groups the games of a best-of-N match between two agents",
+        sharing: Sharing::Public,
+        validation_package: || {
+            hdk::ValidationPackageDefinition::Entry
+        },
+        validation: | validation_data: hdk::EntryValidationData<Series>| {
+            match validation_data {
+                EntryValidationData::Create{ entry, validation_data } => {
+                    let series = Series::from(entry);
+                    if !validation_data.sources().contains(&series.player_1) {
+                        return Err("Cannot start a series as another agent".into())
+                    }
+                    if series.player_1 == series.player_2 {
+                        return Err("Player 1 and Player 2 must be different agents.".into())
+                    }
+                    if series.best_of % 2 == 0 {
+                        return Err("A series must be best of an odd number of games".into())
+                    }
+                    Ok(())
+                },
+                _ => {
+                    Err("Cannot modify or delete a series".into())
+                }
+            }
+        },
+        links: [
+            to!(
+                "game",
+                link_type: "series->game",
+                validation_package: || { hdk::ValidationPackageDefinition::Entry },
+                validation: | validation_data: hdk::LinkValidationData| {
+                    let link = LinkChange::from_validation_data(validation_data);
+                    check_series_link(&link, &link.base, &link.target)
+                }
+            ),
+            from!(
+                "game",
+                link_type: "in_series",
+                validation_package: || { hdk::ValidationPackageDefinition::Entry },
+                validation: | validation_data: hdk::LinkValidationData| {
+                    let link = LinkChange::from_validation_data(validation_data);
+                    check_series_link(&link, &link.target, &link.base)
+                }
+            )
+        ]
+    )
+}
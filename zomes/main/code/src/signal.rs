@@ -0,0 +1,36 @@
+use hdk::{
+    holochain_persistence_api::{
+        cas::content::Address,
+    },
+    holochain_json_api::{
+        error::JsonError, json::JsonString,
+    },
+};
+
+use crate::Outcome;
+
+/// Pushed to clients subscribed to this agent's conductor whenever a game changes,
+/// so a UI can update without polling `get_state`
+#[derive(Serialize, Deserialize, Debug, DefaultJson, Clone)]
+pub enum GameSignal {
+    OpponentMoved{game: Address, move_address: Address},
+    NewProposal{proposal: Address},
+    ProposalAccepted{proposal: Address, game: Address},
+    GameFinished{game: Address, outcome: Outcome},
+}
+
+impl GameSignal {
+    fn name(&self) -> &'static str {
+        match self {
+            GameSignal::OpponentMoved{..} => "opponent_moved",
+            GameSignal::NewProposal{..} => "new_proposal",
+            GameSignal::ProposalAccepted{..} => "proposal_accepted",
+            GameSignal::GameFinished{..} => "game_finished",
+        }
+    }
+}
+
+/// Signals are only a convenience for clients so failing to emit one is not an error
+pub fn emit(signal: GameSignal) {
+    let _ = hdk::emit_signal(signal.name(), JsonString::from(signal));
+}
@@ -0,0 +1,556 @@
+use hdk::{
+    AGENT_ADDRESS,
+    entry_definition::ValidatingEntryType,
+    error::{ZomeApiResult, ZomeApiError},
+    holochain_persistence_api::{
+        cas::content::{AddressableContent, Address},
+    },
+    holochain_json_api::{
+        error::JsonError, json::JsonString,
+    },
+    holochain_core_types::{
+        dna::entry_types::Sharing,
+        validation::EntryValidationData,
+        entry::Entry,
+        link::LinkMatch,
+    }
+};
+
+use crate::game::{self, Game, TimeControl, DEFAULT_INACTIVITY_WINDOW};
+use crate::links::{LinkChange, load_linked};
+use crate::rating::{self, GameResult};
+use crate::VARIANT;
+use rules::pairing;
+
+#[derive(Clone, Debug, Serialize, Deserialize, DefaultJson, PartialEq)]
+pub enum TournamentFormat {
+    RoundRobin,
+    Swiss,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, DefaultJson)]
+pub struct Tournament {
+    pub organiser: Address,
+    pub name: String,
+    // the agents who are invited to play, they must still register
+    pub participants: Vec<Address>,
+    pub format: TournamentFormat,
+    pub rounds: u32,
+    pub variant: String,
+    pub created_at: u32,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, DefaultJson, PartialEq)]
+pub struct Pairing {
+    pub player_1: Address,
+    pub player_2: Address,
+    pub game: Address,
+}
+
+/// The pairings of one round. These are derived deterministically from the tournament,
+/// the earlier rounds and their results, all of which the round refers to, so validation
+/// and any participant can recompute them.
+#[derive(Clone, Debug, Serialize, Deserialize, DefaultJson)]
+pub struct TournamentRound {
+    pub tournament: Address,
+    pub round: u32,
+    // the players who had registered when the first round started
+    pub players: Vec<Address>,
+    pub previous_round: Option<Address>,
+    // the results of every game of the earlier rounds
+    pub results: Vec<Address>,
+    pub pairings: Vec<Pairing>,
+    pub bye: Option<Address>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, DefaultJson)]
+pub struct Standing {
+    pub agent: Address,
+    pub score: f64,
+    // sum of the scores of everyone this agent played, used to break ties
+    pub buchholz: f64,
+    pub wins: u32,
+    pub losses: u32,
+    pub byes: u32,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, DefaultJson)]
+pub struct RoundPairings {
+    pub pairings: Vec<Pairing>,
+    pub bye: Option<Address>,
+}
+
+pub fn handle_create_tournament(
+    name: String,
+    participants: Vec<Address>,
+    format: TournamentFormat,
+    rounds: u32,
+    variant: String,
+    created_at: u32,
+) -> ZomeApiResult<Address> {
+    let tournament = Tournament {
+        organiser: AGENT_ADDRESS.to_string().into(),
+        name,
+        participants,
+        format,
+        rounds,
+        variant,
+        created_at,
+    };
+    let entry = Entry::App(
+        "tournament".into(),
+        tournament.into(),
+    );
+    hdk::commit_entry(&entry)
+}
+
+pub fn handle_register(tournament_addr: Address) -> ZomeApiResult<()> {
+    let tournament: Tournament = hdk::utils::get_as_type(tournament_addr.clone())?;
+    let agent_address: Address = AGENT_ADDRESS.to_string().into();
+    if !tournament.participants.contains(&agent_address) {
+        return Err(ZomeApiError::Internal("You are not a participant of this tournament".into()))
+    }
+    if !get_rounds(&tournament_addr)?.is_empty() {
+        return Err(ZomeApiError::Internal("This tournament has already started".into()))
+    }
+    hdk::link_entries(&tournament_addr, &agent_address, "registered", "")?;
+    Ok(())
+}
+
+/// Work out the pairings for a round without committing anything
+pub fn handle_get_pairings(tournament_addr: Address, round: u32) -> ZomeApiResult<RoundPairings> {
+    let next = next_round(&tournament_addr, round)?;
+    Ok(RoundPairings{pairings: next.pairings, bye: next.bye})
+}
+
+/// Start the next round, creating a game for every pairing
+pub fn handle_start_round(tournament_addr: Address, round: u32) -> ZomeApiResult<Address> {
+    let tournament: Tournament = hdk::utils::get_as_type(tournament_addr.clone())?;
+    if tournament.organiser.to_string() != AGENT_ADDRESS.to_string() {
+        return Err(ZomeApiError::Internal("Only the organiser can start a round".into()))
+    }
+    if get_rounds(&tournament_addr)?.len() as u32 + 1 != round {
+        return Err(ZomeApiError::Internal("Rounds must be started in order".into()))
+    }
+
+    let next = next_round(&tournament_addr, round)?;
+    for pairing in &next.pairings {
+        let game = tournament_game(&tournament, round, &pairing.player_1, &pairing.player_2);
+        let game_addr = game::commit_game(game)?;
+        hdk::link_entries(&tournament_addr, &game_addr, "tournament->game", round.to_string())?;
+    }
+
+    let round_entry = Entry::App(
+        "tournament_round".into(),
+        next.into(),
+    );
+    let round_addr = hdk::commit_entry(&round_entry)?;
+    hdk::link_entries(&tournament_addr, &round_addr, "tournament->round", round.to_string())?;
+    Ok(round_addr)
+}
+
+// Everything a round needs, gathered from the DHT. The pairings come from the same
+// function validation uses, applied to the same entries.
+fn next_round(tournament_addr: &Address, round: u32) -> ZomeApiResult<TournamentRound> {
+    let tournament: Tournament = hdk::utils::get_as_type(tournament_addr.clone())?;
+    if round == 0 || round > tournament.rounds {
+        return Err(ZomeApiError::Internal("This tournament does not have that round".into()))
+    }
+    let earlier_rounds: Vec<TournamentRound> = get_rounds(tournament_addr)?.into_iter().filter(|r| r.round < round).collect();
+    if earlier_rounds.len() as u32 + 1 != round {
+        return Err(ZomeApiError::Internal("The earlier rounds have not all started yet".into()))
+    }
+
+    // every game of the earlier rounds must have a recorded result
+    let linked_results = get_results(tournament_addr)?;
+    let mut results = Vec::new();
+    for pairing in earlier_rounds.iter().flat_map(|earlier| earlier.pairings.iter()) {
+        match linked_results.iter().find(|result| result.game == pairing.game) {
+            Some(result) => results.push(result.clone()),
+            None => return Err(ZomeApiError::Internal("The previous round has not finished yet".into())),
+        }
+    }
+
+    let players = match earlier_rounds.first() {
+        Some(first) => first.players.clone(),
+        None => get_registered(tournament_addr)?,
+    };
+    let RoundPairings{pairings, bye} = pair_round(&tournament, round, &players, &earlier_rounds, &results)
+        .map_err(ZomeApiError::Internal)?;
+
+    Ok(TournamentRound {
+        tournament: tournament_addr.clone(),
+        round,
+        players,
+        previous_round: earlier_rounds.last().map(|previous| Entry::App("tournament_round".into(), previous.clone().into()).address()),
+        results: results.into_iter().map(|result| Entry::App("game_result".into(), result.into()).address()).collect(),
+        pairings,
+        bye,
+    })
+}
+
+// The pairings of a round given who is playing and how the earlier rounds went
+fn pair_round(
+    tournament: &Tournament,
+    round: u32,
+    players: &Vec<Address>,
+    earlier_rounds: &Vec<TournamentRound>,
+    results: &Vec<GameResult>,
+) -> Result<RoundPairings, String> {
+    let (pairs, bye) = match tournament.format {
+        TournamentFormat::RoundRobin => pairing::round_robin_pairs(players.clone(), round),
+        TournamentFormat::Swiss => {
+            let standings = compute_standings(players, earlier_rounds, results);
+            swiss_pairs(standings, earlier_rounds)
+                .ok_or("Every remaining pairing would be a rematch, the tournament has too many rounds".to_string())?
+        },
+    };
+
+    let pairings = pairs.into_iter().map(|(player_1, player_2)| {
+        let game = tournament_game(tournament, round, &player_1, &player_2);
+        Pairing {
+            game: Entry::App("game".into(), game.into()).address(),
+            player_1,
+            player_2,
+        }
+    }).collect();
+    Ok(RoundPairings{pairings, bye})
+}
+
+// Recompute a round from the entries it refers to and check it matches
+fn check_round(round: &TournamentRound, tournament: &Tournament) -> Result<(), String> {
+    if round.round == 0 || round.round > tournament.rounds {
+        return Err("This tournament does not have that round".into())
+    }
+
+    // walk back through the earlier rounds, which must be this tournament's rounds in order
+    let mut earlier_rounds: Vec<TournamentRound> = Vec::new();
+    let mut previous = round.previous_round.clone();
+    while let Some(previous_addr) = previous {
+        let earlier: TournamentRound = hdk::utils::get_as_type(previous_addr)
+            .map_err(|_| "Could not load an earlier round during validation")?;
+        if earlier.tournament != round.tournament || earlier.round + earlier_rounds.len() as u32 + 1 != round.round {
+            return Err("The previous round is not the round before this one".into())
+        }
+        previous = earlier.previous_round.clone();
+        earlier_rounds.insert(0, earlier);
+    }
+    if earlier_rounds.len() as u32 + 1 != round.round {
+        return Err("A round must refer to every earlier round".into())
+    }
+
+    match earlier_rounds.first() {
+        Some(first) => if first.players != round.players {
+            return Err("Players can't join or leave once a tournament has started".into())
+        },
+        None => {
+            let mut expected = round.players.clone();
+            expected.sort_by(|a, b| a.to_string().cmp(&b.to_string()));
+            expected.dedup();
+            if expected != round.players || round.players.iter().any(|player| !tournament.participants.contains(player)) {
+                return Err("The players must be participants of the tournament, sorted by address".into())
+            }
+        },
+    }
+
+    // exactly one result for every game played so far
+    let mut results: Vec<GameResult> = Vec::new();
+    for result_addr in &round.results {
+        let result: GameResult = hdk::utils::get_as_type(result_addr.clone())
+            .map_err(|_| "Could not load a result during validation")?;
+        results.push(result);
+    }
+    let earlier_games: Vec<&Address> = earlier_rounds.iter().flat_map(|earlier| earlier.pairings.iter().map(|pairing| &pairing.game)).collect();
+    let all_counted = results.len() == earlier_games.len()
+        && earlier_games.iter().all(|game| results.iter().any(|result| result.game == **game));
+    if !all_counted {
+        return Err("A round can only start once every earlier game has finished".into())
+    }
+
+    let expected = pair_round(tournament, round.round, &round.players, &earlier_rounds, &results)?;
+    if expected.pairings != round.pairings || expected.bye != round.bye {
+        return Err("The pairings are not the ones the tournament rules give".into())
+    }
+    Ok(())
+}
+
+/// Count the result of a finished tournament game
+pub fn handle_record_result(tournament_addr: Address, game_addr: Address) -> ZomeApiResult<Address> {
+    let in_tournament = get_rounds(&tournament_addr)?.iter().any(|round| {
+        round.pairings.iter().any(|pairing| pairing.game == game_addr)
+    });
+    if !in_tournament {
+        return Err(ZomeApiError::Internal("This game is not part of the tournament".into()))
+    }
+    let result = rating::get_game_result(&game_addr)?
+        .ok_or(ZomeApiError::Internal("This game has not finished yet".into()))?;
+    let result_addr = Entry::App("game_result".into(), result.into()).address();
+    hdk::link_entries(&tournament_addr, &result_addr, "tournament->result", "")?;
+    Ok(result_addr)
+}
+
+pub fn handle_get_standings(tournament_addr: Address) -> ZomeApiResult<Vec<Standing>> {
+    Ok(compute_standings(
+        &get_registered(&tournament_addr)?,
+        &get_rounds(&tournament_addr)?,
+        &get_results(&tournament_addr)?,
+    ))
+}
+
+/*=====================================
+=            DHT Functions            =
+=====================================*/
+
+// registered players in a fixed order so pairings are the same for everyone
+fn get_registered(tournament_addr: &Address) -> ZomeApiResult<Vec<Address>> {
+    let mut players: Vec<Address> = Vec::new();
+    for player in hdk::get_links(tournament_addr, LinkMatch::Exactly("registered"), LinkMatch::Any)?.addresses() {
+        if !players.contains(&player) {
+            players.push(player);
+        }
+    }
+    players.sort_by(|a, b| a.to_string().cmp(&b.to_string()));
+    Ok(players)
+}
+
+fn get_rounds(tournament_addr: &Address) -> ZomeApiResult<Vec<TournamentRound>> {
+    let mut rounds: Vec<TournamentRound> = hdk::utils::get_links_and_load_type(
+        tournament_addr,
+        LinkMatch::Exactly("tournament->round"),
+        LinkMatch::Any
+    )?;
+    rounds.sort_by_key(|round| round.round);
+    rounds.dedup_by_key(|round| round.round);
+    Ok(rounds)
+}
+
+fn get_results(tournament_addr: &Address) -> ZomeApiResult<Vec<GameResult>> {
+    let mut results: Vec<GameResult> = Vec::new();
+    let linked: Vec<GameResult> = hdk::utils::get_links_and_load_type(
+        tournament_addr,
+        LinkMatch::Exactly("tournament->result"),
+        LinkMatch::Any
+    )?;
+    for result in linked {
+        if !results.contains(&result) {
+            results.push(result);
+        }
+    }
+    Ok(results)
+}
+
+/*=====  End of DHT Functions  ======*/
+
+// The game for a pairing only depends on the tournament, so anyone can compute its address
+fn tournament_game(tournament: &Tournament, round: u32, player_1: &Address, player_2: &Address) -> Game {
+    Game {
+        player_1: player_1.clone(),
+        player_2: player_2.clone(),
+        created_at: tournament.created_at + round,
+        first_player: Some(player_2.clone()),
+        time_control: TimeControl::Unlimited,
+        inactivity_window: DEFAULT_INACTIVITY_WINDOW,
+    }
+}
+
+// Ranks the players by their standings and hands them to the Swiss pairing with who has
+// already met and who has already sat out a round
+fn swiss_pairs(standings: Vec<Standing>, earlier_rounds: &Vec<TournamentRound>) -> Option<(Vec<(Address, Address)>, Option<Address>)> {
+    let ranked: Vec<Address> = standings.into_iter().map(|standing| standing.agent).collect();
+    let have_played = |a: &Address, b: &Address| earlier_rounds.iter().any(|round| {
+        round.pairings.iter().any(|pairing| {
+            (pairing.player_1 == *a && pairing.player_2 == *b) || (pairing.player_1 == *b && pairing.player_2 == *a)
+        })
+    });
+    let had_bye = |player: &Address| earlier_rounds.iter().any(|round| round.bye.as_ref() == Some(player));
+    pairing::swiss_pairs(ranked, have_played, had_bye)
+}
+
+// A win is worth 1, a draw 0.5 and a bye 1. Ties are broken by buchholz, then wins.
+fn compute_standings(players: &Vec<Address>, rounds: &Vec<TournamentRound>, results: &Vec<GameResult>) -> Vec<Standing> {
+    let mut standings: Vec<Standing> = players.iter().map(|player| Standing {
+        agent: player.clone(),
+        score: 0.0,
+        buchholz: 0.0,
+        wins: 0,
+        losses: 0,
+        byes: 0,
+    }).collect();
+
+    for round in rounds {
+        if let Some(bye) = &round.bye {
+            if let Some(standing) = standings.iter_mut().find(|standing| standing.agent == *bye) {
+                standing.score += 1.0;
+                standing.byes += 1;
+            }
+        }
+    }
+
+    let counted: Vec<&GameResult> = results.iter().filter(|result| {
+        rounds.iter().any(|round| round.pairings.iter().any(|pairing| pairing.game == result.game))
+    }).collect();
+
+    for result in &counted {
+        let score = match result.player_1_score() {
+            Some(score) => score,
+            None => continue,
+        };
+        for (player, player_score) in vec![(&result.player_1, score), (&result.player_2, 1.0 - score)] {
+            if let Some(standing) = standings.iter_mut().find(|standing| standing.agent == *player) {
+                standing.score += player_score;
+                if player_score == 1.0 {
+                    standing.wins += 1;
+                } else if player_score == 0.0 {
+                    standing.losses += 1;
+                }
+            }
+        }
+    }
+
+    let scores: Vec<(Address, f64)> = standings.iter().map(|standing| (standing.agent.clone(), standing.score)).collect();
+    let score_of = |agent: &Address| scores.iter().find(|(a, _)| a == agent).map(|(_, score)| *score).unwrap_or(0.0);
+    for result in &counted {
+        if result.player_1_score().is_none() {
+            continue
+        }
+        for (player, opponent) in vec![(&result.player_1, &result.player_2), (&result.player_2, &result.player_1)] {
+            if let Some(standing) = standings.iter_mut().find(|standing| standing.agent == *player) {
+                standing.buchholz += score_of(opponent);
+            }
+        }
+    }
+
+    standings.sort_by(|a, b| {
+        b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal)
+            .then(b.buchholz.partial_cmp(&a.buchholz).unwrap_or(std::cmp::Ordering::Equal))
+            .then(b.wins.cmp(&a.wins))
+            .then(a.agent.to_string().cmp(&b.agent.to_string()))
+    });
+    standings
+}
+
+// A tournament game, or its result, is linked by one of its players, or by the organiser who starts
+// the round, and both players must be participants
+fn check_tournament_players(link: &LinkChange, tournament: &Tournament, player_1: &Address, player_2: &Address) -> Result<(), String> {
+    if !link.authored_by_one_of(&[player_1, player_2, &tournament.organiser]) {
+        return Err("Only the players of a game or the organiser can link it to a tournament".into())
+    }
+    if !tournament.participants.contains(player_1) || !tournament.participants.contains(player_2) {
+        return Err("Only games between participants can be part of a tournament".into())
+    }
+    Ok(())
+}
+
+pub fn tournament_def() -> ValidatingEntryType {
+    entry!(
+        name: "tournament",
+        description: "A competition between a fixed list of agents played over several rounds",
+        sharing: Sharing::Public,
+        validation_package: || {
+            hdk::ValidationPackageDefinition::Entry
+        },
+        validation: | validation_data: hdk::EntryValidationData<Tournament>| {
+            match validation_data {
+                EntryValidationData::Create{ entry, validation_data } => {
+                    let tournament = Tournament::from(entry);
+                    if !validation_data.sources().contains(&tournament.organiser) {
+                        return Err("Cannot organise a tournament as another agent".into())
+                    }
+                    if tournament.participants.len() < 2 {
+                        return Err("A tournament needs at least two participants".into())
+                    }
+                    for (i, participant) in tournament.participants.iter().enumerate() {
+                        if tournament.participants[..i].contains(participant) {
+                            return Err("Participants can only be listed once".into())
+                        }
+                    }
+                    if tournament.rounds == 0 {
+                        return Err("A tournament needs at least one round".into())
+                    }
+                    if tournament.variant != VARIANT {
+                        return Err(format!("Tournaments can only be played with the {} rules", VARIANT))
+                    }
+                    Ok(())
+                },
+                _ => {
+                    Err("Cannot modify or delete a tournament".into())
+                }
+            }
+        },
+        links: [
+            to!(
+                "%agent_id",
+                link_type: "registered",
+                validation_package: || { hdk::ValidationPackageDefinition::Entry },
+                validation: | validation_data: hdk::LinkValidationData| {
+                    let link = LinkChange::from_validation_data(validation_data);
+                    let tournament: Tournament = load_linked(&link.base, "tournament")?;
+                    if !link.authored_by_one_of(&[&link.target]) {
+                        return Err("Agents can only register themselves".into())
+                    }
+                    if !tournament.participants.contains(&link.target) {
+                        return Err("Only participants can register for a tournament".into())
+                    }
+                    Ok(())
+                }
+            ),
+            to!(
+                "game",
+                link_type: "tournament->game",
+                validation_package: || { hdk::ValidationPackageDefinition::Entry },
+                validation: | validation_data: hdk::LinkValidationData| {
+                    let link = LinkChange::from_validation_data(validation_data);
+                    let tournament: Tournament = load_linked(&link.base, "tournament")?;
+                    let game: Game = load_linked(&link.target, "game")?;
+                    check_tournament_players(&link, &tournament, &game.player_1, &game.player_2)
+                }
+            ),
+            to!(
+                "tournament_round",
+                link_type: "tournament->round",
+                validation_package: || { hdk::ValidationPackageDefinition::Entry },
+                validation: | _validation_data: hdk::LinkValidationData| {
+                    Ok(())
+                }
+            ),
+            to!(
+                "game_result",
+                link_type: "tournament->result",
+                validation_package: || { hdk::ValidationPackageDefinition::Entry },
+                validation: | validation_data: hdk::LinkValidationData| {
+                    let link = LinkChange::from_validation_data(validation_data);
+                    let tournament: Tournament = load_linked(&link.base, "tournament")?;
+                    let result: GameResult = load_linked(&link.target, "game result")?;
+                    check_tournament_players(&link, &tournament, &result.player_1, &result.player_2)
+                }
+            )
+        ]
+    )
+}
+
+pub fn tournament_round_def() -> ValidatingEntryType {
+    entry!(
+        name: "tournament_round",
+        description: "The pairings for one round of a tournament",
+        sharing: Sharing::Public,
+        validation_package: || {
+            hdk::ValidationPackageDefinition::Entry
+        },
+        validation: | validation_data: hdk::EntryValidationData<TournamentRound>| {
+            match validation_data {
+                EntryValidationData::Create{ entry, validation_data } => {
+                    let round = TournamentRound::from(entry);
+                    let tournament: Tournament = hdk::utils::get_as_type(round.tournament.clone())
+                        .map_err(|_| "Could not load tournament during validation")?;
+                    if !validation_data.sources().contains(&tournament.organiser) {
+                        return Err("Only the organiser can start a round".into())
+                    }
+                    check_round(&round, &tournament)
+                },
+                _ => {
+                    Err("Cannot modify or delete a tournament round".into())
+                }
+            }
+        }
+    )
+}